@@ -0,0 +1,193 @@
+//! Plays a compiled ink story in the terminal.
+//!
+//! ```text
+//! ink-play [--seed N] [--json | --disassemble] story.json
+//! ```
+//!
+//! Lines and tags are printed as the story continues, and choices are numbered. Enter a number to
+//! make a choice, or one of the commands listed by `:help`.
+//!
+//! With `--json`, the story is instead driven by another process, which sends one JSON request per
+//! line on stdin and reads one JSON response per line from stdout. See `Request` and `Response`.
+//!
+//! With `--disassemble`, the story's content is printed as pseudo-assembly instead of being played.
+//! See `Disassembly`.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use ink_engine_runtime::{serve, Story};
+
+const USAGE: &str = "Usage: ink-play [--seed N] [--json | --disassemble] story.json";
+
+const HELP: &str = "\
+Commands:
+  :save FILE    save the state of the story to a file
+  :load FILE    load a state saved with :save
+  :var NAME     print the value of a global variable
+  :goto PATH    jump to a knot or stitch, such as knot.stitch
+  :seed N       set the seed of the story's random numbers
+  :help         print this help
+  :quit         stop playing";
+
+/// What the player should do after a command.
+enum Flow {
+    /// Keep waiting for a choice.
+    Prompt,
+    /// Continue the story from its (possibly new) position.
+    Resume,
+    Quit,
+}
+
+fn main() {
+    let mut seed = None;
+    let mut json_mode = false;
+    let mut disassemble = false;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--seed" => match args.next().and_then(|seed| seed.parse().ok()) {
+                Some(value) => seed = Some(value),
+                None => fail("--seed requires a number"),
+            },
+            "--json" => json_mode = true,
+            "--disassemble" => disassemble = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => fail(&format!("Unexpected argument: {}", arg)),
+        }
+    }
+    let path = path.unwrap_or_else(|| fail(USAGE));
+    if json_mode && disassemble {
+        fail("--json and --disassemble cannot be used together");
+    }
+
+    let json = fs::read_to_string(&path).unwrap_or_else(|error| fail(&format!("Could not read {}: {}", path, error)));
+    let mut story = Story::from_json(&json).unwrap_or_else(|error| fail(&format!("Could not load {}: {}", path, error)));
+    if disassemble {
+        print!("{}", story.disassemble());
+        return;
+    }
+    if let Some(seed) = seed {
+        story.set_seed(seed);
+    }
+
+    let stdin = io::stdin();
+    let result = if json_mode {
+        serve(&mut story, stdin.lock(), io::stdout().lock())
+    } else {
+        play(&mut story, &mut stdin.lock())
+    };
+    if let Err(error) = result {
+        fail(&error.to_string());
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn play<R>(story: &mut Story, input: &mut R) -> io::Result<()> where R: BufRead {
+    loop {
+        while story.can_continue() {
+            story.continue_story();
+            print_output(story);
+        }
+
+        let choices = story.current_choices();
+        if choices.is_empty() {
+            println!("--- end of story ---");
+        }
+        for (index, choice) in choices.iter().enumerate() {
+            println!("{}: {}", index + 1, choice.text());
+        }
+
+        loop {
+            print!("> ");
+            io::stdout().flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let line = line.trim();
+            if line.is_empty() { continue }
+
+            if line.starts_with(':') {
+                match command(story, &line[1..]) {
+                    Flow::Prompt => continue,
+                    Flow::Resume => break,
+                    Flow::Quit => return Ok(()),
+                }
+            }
+            match line.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= choices.len() => {
+                    story.choose_choice_index(number - 1);
+                    break;
+                }
+                _ => println!("Enter the number of a choice, or :help for commands"),
+            }
+        }
+    }
+}
+
+/// Prints the text and tags of the line the story has just continued to.
+fn print_output(story: &Story) {
+    print!("{}", story.current_text());
+    for tag in story.current_tags() {
+        println!("# {}", tag);
+    }
+}
+
+fn command(story: &mut Story, command: &str) -> Flow {
+    let mut words = command.splitn(2, ' ');
+    let name = words.next().unwrap_or("");
+    let argument = words.next().map(str::trim).unwrap_or("");
+    match (name, argument) {
+        ("help", _) => println!("{}", HELP),
+        ("quit", _) | ("q", _) => return Flow::Quit,
+        ("save", file) if !file.is_empty() => match fs::write(file, story.save_state()) {
+            Ok(()) => println!("Saved to {}", file),
+            Err(error) => println!("Could not save to {}: {}", file, error),
+        },
+        ("load", file) if !file.is_empty() => {
+            let loaded = fs::read_to_string(file)
+                .map_err(|error| error.to_string())
+                .and_then(|json| story.load_state(&json).map_err(|error| error.to_string()));
+            match loaded {
+                Ok(()) => {
+                    println!("Loaded {}", file);
+                    print_output(story);
+                    return Flow::Resume;
+                }
+                Err(error) => println!("Could not load {}: {}", file, error),
+            }
+        }
+        ("var", variable) if !variable.is_empty() => match story.variable(variable) {
+            Some(value) => println!("{} = {}", variable, value),
+            None => println!("There is no variable named {}", variable),
+        },
+        ("goto", path) if !path.is_empty() => {
+            if story.has_path(path) {
+                story.choose_path_string(path);
+                return Flow::Resume;
+            }
+            println!("There is no content at {}", path);
+        }
+        ("seed", seed) => match seed.parse() {
+            Ok(seed) => {
+                story.set_seed(seed);
+                println!("Seed set to {}", seed);
+            }
+            Err(..) => println!(":seed requires a number"),
+        },
+        _ => println!("Unknown command :{}, enter :help for commands", command),
+    }
+    Flow::Prompt
+}
@@ -18,4 +18,9 @@ impl Choice {
     ) -> Self {
         Self { text, target_path, is_invisible_default, thread_at_generation }
     }
+
+    /// The text of the choice, as it should be displayed to the player.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
 }
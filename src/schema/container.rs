@@ -6,6 +6,10 @@ pub struct Container {
     pub(crate) name: String,
 
     pub(crate) content: Vec<Object>,
+    /// Named content which is not part of the flow of this container's content (such as knots, and
+    /// the content of choices), so is only reached by diverting to it. Kept in the order it was
+    /// written in the story.
+    pub(crate) named_only_content: Vec<Rc<Container>>,
     pub(crate) visits_should_be_counted: bool,
     pub(crate) turn_index_should_be_counted: bool,
     pub(crate) counting_at_start_only: bool,
@@ -14,7 +18,48 @@ pub struct Container {
     pub(crate) parent: Option<Pointer>,
 }
 
+/// Something found by `Container::walk`.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum WalkItem<'a> {
+    /// A container, which is found before any of its content.
+    Container(&'a Container),
+    /// Any other object, along with the object before it in the same container.
+    Object(&'a Object, Option<&'a Object>),
+}
+
 impl Container {
+    /// Calls `f` for this container and everything within it, in the order it appears in the
+    /// container, along with its path. The named-only content of each container (such as knots) is
+    /// found after the rest of its content.
+    pub(crate) fn walk<'a, F>(&'a self, f: &mut F) where F: FnMut(&Path, WalkItem<'a>) {
+        self.walk_from(&mut Path::default(), f);
+    }
+
+    fn walk_from<'a, F>(&'a self, path: &mut Path, f: &mut F) where F: FnMut(&Path, WalkItem<'a>) {
+        f(path, WalkItem::Container(self));
+        let mut previous = None;
+        for (index, object) in self.content.iter().enumerate() {
+            if let Object::Container(child) = object {
+                if child.has_valid_name() {
+                    path.join(child.name().to_owned());
+                } else {
+                    path.join(index);
+                }
+                child.walk_from(path, f);
+            } else {
+                path.join(index);
+                f(path, WalkItem::Object(object, previous));
+            }
+            path.parts.pop();
+            previous = Some(object);
+        }
+        for child in &self.named_only_content {
+            path.join(child.name().to_owned());
+            child.walk_from(path, f);
+            path.parts.pop();
+        }
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
@@ -33,20 +78,24 @@ impl Container {
         let mut container = Some(self);
         for component in &path.parts[start..] {
             match component {
-                Component::Index(index) => object = container?.content.get(*index).cloned(),
+                Component::Index(index) => object = Some(container?.content.get(*index)?.clone()),
                 Component::Name(name) => {
-                    for o in &container?.content {
-                        match TryAsRef::<dyn NamedObject>::try_as_ref(o) {
-                            Some(named_object) if named_object.name() == name => {
-                                object = Some(o.clone());
-                                break;
-                            }
-                            _ => {}
-                        }
-                    }
-                    return None;
+                    let container = container?;
+                    let named_object = container.content
+                        .iter()
+                        .find(|o| match TryAsRef::<dyn NamedObject>::try_as_ref(*o) {
+                            Some(named_object) => named_object.name() == name,
+                            None => false,
+                        })
+                        .cloned()
+                        .or_else(|| container.named_only_content
+                            .iter()
+                            .find(|child| child.name() == name)
+                            .map(|child| Object::Container(child.clone()))
+                        )?;
+                    object = Some(named_object);
                 }
-                Component::Parent => object = self.parent.as_ref()?.resolve(),
+                Component::Parent => object = container?.parent.as_ref()?.resolve(),
             }
 
             container = object
@@ -63,3 +112,85 @@ impl NamedObject for Container {
         self.name.as_str()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Value;
+
+    /// A root containing a knot, which contains a line of text and a stitch.
+    fn story_content() -> Rc<Container> {
+        Rc::new_cyclic(|root| {
+            let knot = Rc::new_cyclic(|knot| {
+                let stitch = Rc::new(Container {
+                    name: "stitch".to_owned(),
+                    parent: Some(Pointer { container: Some(knot.clone()), index: None }),
+                    ..Container::default()
+                });
+                Container {
+                    name: "knot".to_owned(),
+                    content: vec![Object::Value(Value::String("text".to_owned())), Object::Container(stitch)],
+                    parent: Some(Pointer { container: Some(root.clone()), index: None }),
+                    ..Container::default()
+                }
+            });
+            Container { content: vec![Object::Container(knot)], ..Container::default() }
+        })
+    }
+
+    fn name_at(root: &Container, path: &str) -> Option<String> {
+        match root.content_at_path(&path.parse().unwrap())? {
+            Object::Container(container) => Some(container.name.clone()),
+            Object::Value(Value::String(text)) => Some(text),
+            object => panic!("unexpected object {:?}", object),
+        }
+    }
+
+    #[test]
+    fn content_at_path_by_name_and_index() {
+        let root = story_content();
+        assert_eq!(name_at(&root, "knot").as_deref(), Some("knot"));
+        assert_eq!(name_at(&root, "knot.stitch").as_deref(), Some("stitch"));
+        assert_eq!(name_at(&root, "0.1").as_deref(), Some("stitch"));
+        assert_eq!(name_at(&root, "knot.0").as_deref(), Some("text"));
+    }
+
+    #[test]
+    fn content_at_path_parent() {
+        let root = story_content();
+        assert_eq!(name_at(&root, "knot.stitch.^").as_deref(), Some("knot"));
+        assert_eq!(name_at(&root, "knot.stitch.^.0").as_deref(), Some("text"));
+    }
+
+    #[test]
+    fn walk_visits_named_only_content() {
+        let content = super::super::ink_json::load(r##"{"inkVersion":19,"root":[["^Hello",null],"done",{"knot":["^In knot",{"stitch":["^In stitch",null]}]}]}"##).unwrap();
+        let mut walked = vec![];
+        content.main_container.walk(&mut |path, item| {
+            let item = match item {
+                WalkItem::Container(..) => "container".to_owned(),
+                WalkItem::Object(object, previous) => format!("{} after {:?}", object, previous.map(Object::to_string)),
+            };
+            walked.push(format!("{}: {}", path, item));
+        });
+        assert_eq!(walked, vec![
+            ": container",
+            "0: container",
+            "0.0: \"Hello\" after None",
+            "1: Done after Some(\"container\")",
+            "knot: container",
+            "knot.0: \"In knot\" after None",
+            "knot.stitch: container",
+            "knot.stitch.0: \"In stitch\" after None",
+        ]);
+    }
+
+    #[test]
+    fn content_at_path_missing() {
+        let root = story_content();
+        assert_eq!(name_at(&root, "other"), None);
+        assert_eq!(name_at(&root, "knot.other"), None);
+        assert_eq!(name_at(&root, "knot.5"), None);
+        assert_eq!(name_at(&root, "knot.0.0"), None);
+    }
+}
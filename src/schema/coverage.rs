@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use serde::{Serialize, Deserialize};
+use super::{Container, NamedObject, Object, Path, Value, WalkItem};
+
+/// Records how many times each container of a story has been entered, regardless of whether the
+/// story itself counts visits to that container.
+///
+/// Coverage is collected by a `Story` once enabled with `Story::enable_coverage`. Coverage from many
+/// sessions can be accumulated by saving it (it implements `Serialize`/`Deserialize`) and merging
+/// it, and then a `CoverageReport` can be produced to find content which was never reached.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Coverage {
+    hits: BTreeMap<Path, usize>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record(&mut self, path: Path) {
+        *self.hits.entry(path).or_default() += 1;
+    }
+
+    /// The number of times the container at this path was entered.
+    pub fn hits(&self, path: &Path) -> usize {
+        self.hits.get(path).cloned().unwrap_or(0)
+    }
+
+    /// Adds the hits from another coverage record to this one.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (path, hits) in &other.hits {
+            *self.hits.entry(path.clone()).or_default() += hits;
+        }
+    }
+
+    /// Produces a report of the coverage of all the named content (knots, stitches, gathers, etc.)
+    /// and text within the root container.
+    pub fn report(&self, root: &Container) -> CoverageReport {
+        let mut report = CoverageReport::default();
+        root.walk(&mut |path, item| match item {
+            // names starting with $ are generated by the compiler, not written in ink
+            WalkItem::Container(container) if container.has_valid_name() && !container.name().starts_with('$') => {
+                report.containers.push(ContainerCoverage { path: path.clone(), hits: self.hits(path) });
+            }
+            WalkItem::Object(Object::Value(Value::String(text)), _) if !text.trim().is_empty() && self.hits(&path.without_last_component()) == 0 => {
+                report.unvisited_lines.push(UnvisitedLine { path: path.clone(), text: text.clone() });
+            }
+            _ => {}
+        });
+        report
+    }
+}
+
+/// The number of times a named container was entered.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContainerCoverage {
+    pub path: Path,
+    pub hits: usize,
+}
+
+/// A piece of text in a container which was never entered.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnvisitedLine {
+    pub path: Path,
+    pub text: String,
+}
+
+/// A report of which content of a story was reached, produced by `Coverage::report`. The report
+/// implements `Display`, producing a plain text version of the report.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Every named container, in the order it appears in the story.
+    pub containers: Vec<ContainerCoverage>,
+    /// Every piece of text which was never reached, in the order it appears in the story.
+    pub unvisited_lines: Vec<UnvisitedLine>,
+}
+
+impl CoverageReport {
+    /// The named containers which were never entered.
+    pub fn unvisited_containers(&self) -> impl Iterator<Item = &ContainerCoverage> {
+        self.containers.iter().filter(|container| container.hits == 0)
+    }
+}
+
+impl Display for CoverageReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let visited = self.containers.iter().filter(|container| container.hits > 0).count();
+        writeln!(f, "Containers: {}/{} visited", visited, self.containers.len())?;
+        for container in &self.containers {
+            writeln!(f, "{:>8}  {}", container.hits, container.path)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Unvisited lines: {}", self.unvisited_lines.len())?;
+        for line in &self.unvisited_lines {
+            writeln!(f, "{}: {:?}", line.path, line.text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Story;
+
+    #[test]
+    fn report_includes_recorded_visits() {
+        let json = r#"{"inkVersion":19,"root":[[{"->":"visited"},null],"done",{"visited":["^Here","\n","end",null],"unvisited":["^Not here","\n",{"stitch":["^Nor here","\n","end",null]}]}]}"#;
+        let mut story = Story::from_json(json).unwrap();
+        story.enable_coverage(Coverage::new());
+        story.continue_story();
+
+        let report = story.coverage_report().unwrap();
+        let containers = report.containers
+            .iter()
+            .map(|container| (container.path.to_string(), container.hits))
+            .collect::<Vec<_>>();
+        assert_eq!(containers, vec![("visited".to_owned(), 1), ("unvisited".to_owned(), 0), ("unvisited.stitch".to_owned(), 0)]);
+        let unvisited_lines = report.unvisited_lines
+            .iter()
+            .map(|line| (line.path.to_string(), line.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(unvisited_lines, vec![("unvisited.0".to_owned(), "Not here"), ("unvisited.stitch.0".to_owned(), "Nor here")]);
+    }
+}
@@ -0,0 +1,185 @@
+use super::{LimitExceeded, Object, ObjectKind, Path, Story};
+
+/// A condition on which the `Debugger` pauses the story, checked before each object is executed.
+#[derive(Clone, Debug)]
+pub enum Breakpoint {
+    /// Pauses when execution enters the content at this path (such as a knot or stitch), or reaches
+    /// the specific object at this path.
+    Path(Path),
+    /// Pauses before every object of this kind.
+    ObjectKind(ObjectKind),
+    /// Pauses before every assignment to the variable with this name.
+    VariableAssignment(String),
+}
+
+/// The reason a `Debugger` returned control to the host.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum DebugStop {
+    /// The requested step was completed.
+    Stepped,
+    /// The story was paused before executing an object that matched the breakpoint at this index.
+    Breakpoint(usize),
+    /// The story cannot continue, because it has ended or is waiting for a choice to be made.
+    Stopped,
+    /// The story exceeded one of its `Limits` while executing an object.
+    LimitExceeded(LimitExceeded),
+}
+
+/// Steps through a `Story` one object at a time, pausing on `Breakpoint`s.
+///
+/// While paused, the state of the story can be inspected through `Story::current_path`,
+/// `Story::evaluation_stack`, `Story::temporary_variables` and `Story::call_stack_depth`.
+///
+/// Each call to the debugger counts as a single `continue` towards the story's `Limits`, and starts
+/// a new line of output if the previous line was completed.
+///
+/// Note that the debugger executes the story's objects directly, so invisible default choices are
+/// not followed automatically as they are when continuing the story normally.
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    previous_path: Option<Path>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a breakpoint, returning its index.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    /// Removes the breakpoint at this index. The indices of all later breakpoints are shifted down.
+    pub fn remove_breakpoint(&mut self, index: usize) -> Breakpoint {
+        self.breakpoints.remove(index)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Executes exactly one object, ignoring breakpoints.
+    pub fn step_object(&mut self, story: &mut Story) -> DebugStop {
+        story.begin_steps();
+        self.step(story)
+    }
+
+    fn step(&mut self, story: &mut Story) -> DebugStop {
+        if !story.can_continue() {
+            return DebugStop::Stopped;
+        }
+        self.previous_path = story.current_path();
+        match story.step() {
+            Ok(()) => DebugStop::Stepped,
+            Err(error) => DebugStop::LimitExceeded(error),
+        }
+    }
+
+    /// Executes one object, but if that object calls a function or tunnel, continues until it has
+    /// returned.
+    pub fn step_over(&mut self, story: &mut Story) -> DebugStop {
+        let depth = story.call_stack_depth();
+        self.run(story, |story| story.call_stack_depth() <= depth)
+    }
+
+    /// Continues until the current function or tunnel returns.
+    pub fn step_out(&mut self, story: &mut Story) -> DebugStop {
+        let depth = story.call_stack_depth();
+        self.run(story, |story| story.call_stack_depth() < depth)
+    }
+
+    /// Continues until a breakpoint is hit, or the story stops.
+    pub fn resume(&mut self, story: &mut Story) -> DebugStop {
+        self.run(story, |_| false)
+    }
+
+    /// Executes at least one object, then continues until either `done` returns true, a breakpoint
+    /// is hit, or the story stops.
+    fn run<F>(&mut self, story: &mut Story, done: F) -> DebugStop where F: Fn(&Story) -> bool {
+        story.begin_steps();
+        match self.step(story) {
+            DebugStop::Stepped => {}
+            stop => return stop,
+        }
+        loop {
+            if !story.can_continue() {
+                return DebugStop::Stopped;
+            }
+            if done(story) {
+                return DebugStop::Stepped;
+            }
+            if let Some(index) = self.hit_breakpoint(story) {
+                return DebugStop::Breakpoint(index);
+            }
+            if let DebugStop::LimitExceeded(error) = self.step(story) {
+                return DebugStop::LimitExceeded(error);
+            }
+        }
+    }
+
+    /// The index of the first breakpoint matching the object that will be executed next, if any.
+    fn hit_breakpoint(&self, story: &Story) -> Option<usize> {
+        let (pointer, object) = story.next_object()?;
+        let path = pointer.path();
+        self.breakpoints
+            .iter()
+            .position(|breakpoint| match breakpoint {
+                Breakpoint::Path(target) => {
+                    // content is entered from outside it, or by going to its start (such as a knot
+                    // which diverts back to itself)
+                    let inside = |path: &Option<Path>| path.as_ref().map(|path| path.starts_with(target)).unwrap_or(false);
+                    (inside(&path) && !inside(&self.previous_path)) || (path.is_some() && path == story.first_object_path(target))
+                }
+                Breakpoint::ObjectKind(kind) => object.kind() == *kind,
+                Breakpoint::VariableAssignment(name) => match &object {
+                    Object::VariableAssignment(assignment) => &assignment.variable_name == name,
+                    _ => false,
+                },
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Limits;
+
+    #[test]
+    fn path_breakpoints_pause_on_entering_content() {
+        let json = r#"{"inkVersion":19,"root":[[{"->":"start"},null],"done",{"start":["^Start","\n",{"->":"other.part"},null],"other":["^Other","\n",{"part":["^Part","\n","end",null]}]}]}"#;
+        let mut story = Story::from_json(json).unwrap();
+        let mut debugger = Debugger::new();
+        debugger.add_breakpoint(Breakpoint::Path("other.part".parse().unwrap()));
+        debugger.add_breakpoint(Breakpoint::Path("start".parse().unwrap()));
+
+        assert_eq!(debugger.resume(&mut story), DebugStop::Breakpoint(1));
+        assert_eq!(story.current_path().map(|path| path.to_string()), Some("start.0".to_owned()));
+        assert_eq!(debugger.resume(&mut story), DebugStop::Breakpoint(0));
+        assert_eq!(story.current_path().map(|path| path.to_string()), Some("other.part.0".to_owned()));
+        assert_eq!(story.current_text(), "Start\n");
+        assert_eq!(debugger.resume(&mut story), DebugStop::Stopped);
+        assert_eq!(story.current_text(), "Part\n");
+    }
+
+    #[test]
+    fn path_breakpoints_pause_each_time_a_knot_loops() {
+        let json = r#"{"inkVersion":19,"root":[[{"->":"loop"},null],"done",{"loop":["^Loop",{"->":"loop"},null]}]}"#;
+        let mut story = Story::from_json(json).unwrap();
+        // each resume takes two steps, so this also checks that steps are counted from each resume
+        story.set_limits(Limits { max_steps_per_continue: Some(5), ..Limits::default() });
+        let mut debugger = Debugger::new();
+        debugger.add_breakpoint(Breakpoint::Path("loop".parse().unwrap()));
+
+        for _ in 0..5 {
+            assert_eq!(debugger.resume(&mut story), DebugStop::Breakpoint(0));
+            assert_eq!(story.current_path().map(|path| path.to_string()), Some("loop.0".to_owned()));
+        }
+    }
+}
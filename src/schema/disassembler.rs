@@ -0,0 +1,101 @@
+use std::fmt::{self, Display, Formatter};
+use super::{Container, DivertTarget, Object, Path, WalkItem};
+
+/// Renders a container and all of its content as indented pseudo-assembly, with the path of each
+/// object, for understanding what the ink compiler produced.
+///
+/// Relative divert and choice targets are shown along with the absolute path they resolve to.
+pub struct Disassembly<'a> {
+    root: &'a Container,
+}
+
+impl<'a> Disassembly<'a> {
+    pub fn new(root: &'a Container) -> Self {
+        Self { root }
+    }
+
+    fn write_container(f: &mut Formatter, container: &Container, path: &Path) -> fmt::Result {
+        let indent = "  ".repeat(path.parts.len());
+        let mut flags = vec![];
+        if container.visits_should_be_counted { flags.push("visits"); }
+        if container.turn_index_should_be_counted { flags.push("turns"); }
+        if container.counting_at_start_only { flags.push("start only"); }
+        let name = if path.is_empty() { "(root)".to_owned() } else { path.to_string() };
+        if flags.is_empty() {
+            writeln!(f, "{}{}:", indent, name)?;
+        } else {
+            writeln!(f, "{}{}: [{}]", indent, name, flags.join(", "))?;
+        }
+        Ok(())
+    }
+
+    fn write_object(f: &mut Formatter, object: &Object, path: &Path) -> fmt::Result {
+        let indent = "  ".repeat(path.parts.len());
+        write!(f, "{}{}: {}", indent, path, object)?;
+        match object {
+            Object::Divert(divert) => match &divert.target {
+                DivertTarget::Path(target) if target.is_relative => write!(f, " (=> {})", path.appending(target))?,
+                _ => {}
+            },
+            Object::ChoicePoint(choice_point) => {
+                if choice_point.path_on_choice.is_relative {
+                    write!(f, " (=> {})", path.appending(&choice_point.path_on_choice))?;
+                }
+                let mut flags = vec![];
+                if choice_point.has_condition { flags.push("condition"); }
+                if choice_point.has_start_content { flags.push("start content"); }
+                if choice_point.has_choice_only_content { flags.push("choice only content"); }
+                if choice_point.once_only { flags.push("once only"); }
+                if choice_point.is_invisible_default { flags.push("invisible default"); }
+                if !flags.is_empty() {
+                    write!(f, " [{}]", flags.join(", "))?;
+                }
+            }
+            _ => {}
+        }
+        writeln!(f)
+    }
+}
+
+impl<'a> Display for Disassembly<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut result = Ok(());
+        self.root.walk(&mut |path, item| {
+            if result.is_err() { return }
+            result = match item {
+                WalkItem::Container(container) => Self::write_container(f, container, path),
+                WalkItem::Object(object, _) => Self::write_object(f, object, path),
+            };
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Story;
+
+    #[test]
+    fn disassembles_each_object_with_its_path() {
+        let story = Story::from_json(r##"{"inkVersion":19,"root":[[{"->":"start"},null],"done",{"start":["ev",1,{"VAR=":"x","re":true},"/ev","ev","str","^Go","/str","/ev",{"*":".^.c-0","flg":20},{"c-0":[{"->":"start"},{"#f":5}]}]}]}"##).unwrap();
+        assert_eq!(story.disassemble().lines().collect::<Vec<_>>(), vec![
+            "(root):",
+            "  0:",
+            "    0.0: divert -> start",
+            "  1: Done",
+            "  start:",
+            "    start.0: EvalStart",
+            "    start.1: 1",
+            "    start.2: x =",
+            "    start.3: EvalEnd",
+            "    start.4: EvalStart",
+            "    start.5: BeginString",
+            r#"    start.6: "Go""#,
+            "    start.7: EndString",
+            "    start.8: EvalEnd",
+            "    start.9: choice point -> .^.c-0 (=> start.c-0) [choice only content, once only]",
+            "    start.c-0: [visits, start only]",
+            "      start.c-0.0: divert -> start",
+        ]);
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::rc::{Rc, Weak};
+use serde::{Deserialize, Deserializer};
+use serde::de::{self, Error as _, MapAccess, SeqAccess, Visitor};
+use super::*;
+
+/// A JSON value which, unlike `serde_json::Value`, keeps the keys of objects in the order they were
+/// written, as that is the order the knots of the story were declared in.
+enum Token {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Token>),
+    Object(Vec<(String, Token)>),
+}
+
+impl<'de> Deserialize<'de> for Token {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TokenVisitor;
+
+        impl<'de> Visitor<'de> for TokenVisitor {
+            type Value = Token;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "any JSON value")
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Token, E> { Ok(Token::Null) }
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Token, E> { Ok(Token::Bool(value)) }
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Token, E> { Ok(Token::Int(value)) }
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Token, E> { Ok(Token::Int(value as i64)) }
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Token, E> { Ok(Token::Float(value)) }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Token, E> { Ok(Token::String(value.to_owned())) }
+            fn visit_string<E: de::Error>(self, value: String) -> Result<Token, E> { Ok(Token::String(value)) }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Token, A::Error> {
+                let mut tokens = vec![];
+                while let Some(token) = seq.next_element()? {
+                    tokens.push(token);
+                }
+                Ok(Token::Array(tokens))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Token, A::Error> {
+                let mut entries = vec![];
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Token::Object(entries))
+            }
+        }
+
+        deserializer.deserialize_any(TokenVisitor)
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Token::Null => write!(f, "null"),
+            Token::Bool(value) => write!(f, "{}", value),
+            Token::Int(value) => write!(f, "{}", value),
+            Token::Float(value) => write!(f, "{}", value),
+            Token::String(value) => write!(f, "{:?}", value),
+            Token::Array(..) => write!(f, "array"),
+            Token::Object(entries) => {
+                let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+                write!(f, "object {{{}}}", keys.join(", "))
+            }
+        }
+    }
+}
+
+/// The content of a story, as loaded from the JSON produced by the ink compiler.
+pub(crate) struct StoryContent {
+    pub main_container: Rc<Container>,
+    pub list_definitions: ListDefinitions,
+}
+
+/// Loads the content of a story from the JSON produced by the ink compiler.
+pub(crate) fn load(json: &str) -> Result<StoryContent, serde_json::Error> {
+    // the compiler writes a byte order mark at the start of the file
+    let json = json.trim_start_matches('\u{feff}');
+    let entries = match serde_json::from_str(json)? {
+        Token::Object(entries) => entries,
+        token => return Err(serde_json::Error::custom(format!("Expected the story to be a JSON object, found {}", token))),
+    };
+
+    let mut version = None;
+    let mut root = None;
+    let mut list_definitions = vec![];
+    for (key, token) in entries {
+        match (key.as_str(), token) {
+            ("inkVersion", Token::Int(value)) => version = Some(value),
+            ("root", token) => root = Some(token),
+            ("listDefs", Token::Object(lists)) => {
+                for (name, items) in lists {
+                    list_definitions.push(list_definition(name, items)?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let version = version.ok_or_else(|| serde_json::Error::custom("Version of ink could not be found"))?;
+    if version > Story::INK_VERSION_CURRENT as i64 {
+        return Err(serde_json::Error::custom(format!("Version of ink used to build story ({}) was newer than the current version of the engine ({})", version, Story::INK_VERSION_CURRENT)));
+    }
+    if version < Story::INK_VERSION_MINIMUM_COMPATIBLE as i64 {
+        return Err(serde_json::Error::custom(format!("Version of ink used to build story ({}) is too old to be loaded by this version of the engine", version)));
+    }
+
+    let root = match root {
+        Some(Token::Array(tokens)) => tokens,
+        _ => return Err(serde_json::Error::custom("Root node for ink not found")),
+    };
+    Ok(StoryContent {
+        main_container: container(root, None, None)?,
+        list_definitions: ListDefinitions::new(list_definitions),
+    })
+}
+
+fn list_definition(name: String, items: Token) -> Result<ListDefinition, serde_json::Error> {
+    let items = match items {
+        Token::Object(items) => items,
+        token => return Err(serde_json::Error::custom(format!("Expected the items of list {} to be an object, found {}", name, token))),
+    };
+    let items = items
+        .into_iter()
+        .map(|(item, value)| match value {
+            Token::Int(value) => Ok(ListEntry::new(name.clone(), item, value)),
+            token => Err(serde_json::Error::custom(format!("Expected the value of list item {}.{} to be an int, found {}", name, item, token))),
+        })
+        .collect::<Result<BTreeSet<_>, _>>()?;
+    Ok(ListDefinition { name, items })
+}
+
+/// Builds a container from its array of content. The last element of the array is either `null`,
+/// or an object containing the container's named-only content, its count flags (`#f`) and its
+/// name (`#n`).
+fn container(mut tokens: Vec<Token>, name: Option<String>, parent: Option<&Weak<Container>>) -> Result<Rc<Container>, serde_json::Error> {
+    let terminator = tokens.pop();
+    let mut error = None;
+    let container = Rc::new_cyclic(|this| {
+        let mut container = Container {
+            name: name.unwrap_or_default(),
+            parent: parent.map(|parent| Pointer { container: Some(parent.clone()), index: None }),
+            ..Container::default()
+        };
+
+        let content = tokens
+            .into_iter()
+            .map(|token| object(token, this))
+            .collect::<Result<_, _>>();
+        match content {
+            Ok(content) => container.content = content,
+            Err(content_error) => error = Some(content_error),
+        }
+
+        if let Some(Token::Object(entries)) = terminator {
+            for (key, token) in entries {
+                match (key.as_str(), token) {
+                    ("#f", Token::Int(flags)) => {
+                        container.visits_should_be_counted = flags & 0x1 != 0;
+                        container.turn_index_should_be_counted = flags & 0x2 != 0;
+                        container.counting_at_start_only = flags & 0x4 != 0;
+                    }
+                    ("#n", Token::String(name)) => container.name = name,
+                    (_, Token::Array(tokens)) => match self::container(tokens, Some(key), Some(this)) {
+                        Ok(child) => container.named_only_content.push(child),
+                        Err(child_error) => error = Some(child_error),
+                    },
+                    (_, token) => error = Some(serde_json::Error::custom(format!("Expected named content {} to be a container, found {}", key, token))),
+                }
+            }
+        }
+        container
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(container),
+    }
+}
+
+/// Converts a piece of the content of the container `parent`.
+fn object(token: Token, parent: &Weak<Container>) -> Result<Object, serde_json::Error> {
+    let object = match token {
+        Token::Bool(value) => Object::Value(value.into()),
+        Token::Int(value) => Object::Value(Value::Int(value)),
+        Token::Float(value) => Object::Value(Value::Float(value)),
+        Token::String(string) => string_object(string)?,
+        Token::Array(tokens) => Object::Container(container(tokens, None, Some(parent))?),
+        Token::Object(entries) => dictionary_object(entries)?,
+        Token::Null => return Err(serde_json::Error::custom("Unexpected null in the content of a container")),
+    };
+    Ok(object)
+}
+
+fn string_object(string: String) -> Result<Object, serde_json::Error> {
+    if let Some(text) = string.strip_prefix('^') {
+        return Ok(Object::Value(Value::String(text.to_owned())));
+    }
+
+    use ControlCommand::*;
+    use NativeFunctionCall::*;
+    let object = match string.as_str() {
+        "\n" => Object::Value(Value::String(string)),
+        "<>" => Object::Glue(Rc::new(Glue)),
+        "void" => Object::Void,
+
+        "ev" => Object::ControlCommand(EvalStart),
+        "out" => Object::ControlCommand(EvalOutput),
+        "/ev" => Object::ControlCommand(EvalEnd),
+        "du" => Object::ControlCommand(Duplicate),
+        "pop" => Object::ControlCommand(PopEvaluatedValue),
+        "~ret" => Object::ControlCommand(PopFunction),
+        "->->" => Object::ControlCommand(PopTunnel),
+        "str" => Object::ControlCommand(BeginString),
+        "/str" => Object::ControlCommand(EndString),
+        "nop" => Object::ControlCommand(NoOp),
+        "choiceCnt" => Object::ControlCommand(ChoiceCount),
+        "turn" => Object::ControlCommand(Turns),
+        "turns" => Object::ControlCommand(TurnsSince),
+        "readc" => Object::ControlCommand(ReadCount),
+        "rnd" => Object::ControlCommand(Random),
+        "srnd" => Object::ControlCommand(SeedRandom),
+        "visit" => Object::ControlCommand(VisitIndex),
+        "seq" => Object::ControlCommand(SequenceShuffleIndex),
+        "thread" => Object::ControlCommand(StartThread),
+        "done" => Object::ControlCommand(Done),
+        "end" => Object::ControlCommand(End),
+        "listInt" => Object::ControlCommand(ListFromInt),
+        "range" => Object::ControlCommand(ListRange),
+        "lrnd" => Object::ControlCommand(ListRandom),
+
+        "+" => Object::NativeFunctionCall(Rc::new(Add)),
+        "-" => Object::NativeFunctionCall(Rc::new(Subtract)),
+        "/" => Object::NativeFunctionCall(Rc::new(Divide)),
+        "*" => Object::NativeFunctionCall(Rc::new(Multiply)),
+        "%" => Object::NativeFunctionCall(Rc::new(Mod)),
+        "_" => Object::NativeFunctionCall(Rc::new(Negate)),
+        "==" => Object::NativeFunctionCall(Rc::new(Equal)),
+        ">" => Object::NativeFunctionCall(Rc::new(Greater)),
+        "<" => Object::NativeFunctionCall(Rc::new(Less)),
+        ">=" => Object::NativeFunctionCall(Rc::new(GreaterThanOrEquals)),
+        "<=" => Object::NativeFunctionCall(Rc::new(LessThanOrEquals)),
+        "!=" => Object::NativeFunctionCall(Rc::new(NotEquals)),
+        "!" => Object::NativeFunctionCall(Rc::new(Not)),
+        "&&" => Object::NativeFunctionCall(Rc::new(And)),
+        "||" => Object::NativeFunctionCall(Rc::new(Or)),
+        "MIN" => Object::NativeFunctionCall(Rc::new(Min)),
+        "MAX" => Object::NativeFunctionCall(Rc::new(Max)),
+        "POW" => Object::NativeFunctionCall(Rc::new(Pow)),
+        "FLOOR" => Object::NativeFunctionCall(Rc::new(Floor)),
+        "CEILING" => Object::NativeFunctionCall(Rc::new(Ceiling)),
+        "INT" => Object::NativeFunctionCall(Rc::new(Int)),
+        "FLOAT" => Object::NativeFunctionCall(Rc::new(Float)),
+        "?" => Object::NativeFunctionCall(Rc::new(Has)),
+        "!?" => Object::NativeFunctionCall(Rc::new(Hasnt)),
+        // `^` is written as `L^` to avoid confusion with text, which also starts with `^`
+        "L^" => Object::NativeFunctionCall(Rc::new(Intersect)),
+        "LIST_MIN" => Object::NativeFunctionCall(Rc::new(ListMin)),
+        "LIST_MAX" => Object::NativeFunctionCall(Rc::new(ListMax)),
+        "LIST_ALL" => Object::NativeFunctionCall(Rc::new(All)),
+        "LIST_COUNT" => Object::NativeFunctionCall(Rc::new(Count)),
+        "LIST_VALUE" => Object::NativeFunctionCall(Rc::new(ValueOfList)),
+        "LIST_INVERT" => Object::NativeFunctionCall(Rc::new(Invert)),
+
+        _ => return Err(serde_json::Error::custom(format!("Failed to convert {:?} to a runtime object", string))),
+    };
+    Ok(object)
+}
+
+fn dictionary_object(entries: Vec<(String, Token)>) -> Result<Object, serde_json::Error> {
+    let get = |key: &str| entries.iter().find(|(k, _)| k == key).map(|(_, token)| token);
+    let string = |key: &str| match get(key) {
+        Some(Token::String(value)) => Ok(value.clone()),
+        token => Err(serde_json::Error::custom(format!("Expected {} to be a string, found {}", key, token.map(ToString::to_string).unwrap_or_default()))),
+    };
+    let int = |key: &str| match get(key) {
+        Some(Token::Int(value)) => Some(*value),
+        _ => None,
+    };
+    let path = |key: &str| string(key).and_then(|path| path.parse::<Path>().map_err(serde_json::Error::custom));
+
+    if get("^->").is_some() {
+        return Ok(Object::Value(Value::DivertTarget(path("^->")?)));
+    }
+
+    if get("^var").is_some() {
+        let context = match int("ci") {
+            None | Some(-1) => VariableContext::Unknown,
+            Some(0) => VariableContext::Global,
+            Some(index) => VariableContext::Temporary(index as usize - 1),
+        };
+        return Ok(Object::Value(Value::VariablePointer(VariablePointer { name: string("^var")?, context })));
+    }
+
+    let divert = [("->", false, PushPopType::Function), ("f()", true, PushPopType::Function), ("->t->", true, PushPopType::Tunnel)]
+        .iter()
+        .find(|(key, _, _)| get(key).is_some());
+    if let Some((key, pushes_to_stack, stack_push_type)) = divert {
+        let target = if get("var").is_some() {
+            DivertTarget::Variable(string(key)?)
+        } else {
+            DivertTarget::Path(path(key)?)
+        };
+        return Ok(Object::Divert(Rc::new(Divert {
+            target,
+            pushes_to_stack: *pushes_to_stack,
+            stack_push_type: *stack_push_type,
+            is_conditional: get("c").is_some(),
+        })));
+    }
+
+    if get("x()").is_some() {
+        return Ok(Object::Divert(Rc::new(Divert {
+            target: DivertTarget::External { path: string("x()")?, args: int("exArgs").unwrap_or(0) as i32 },
+            pushes_to_stack: false,
+            stack_push_type: PushPopType::Function,
+            is_conditional: get("c").is_some(),
+        })));
+    }
+
+    if get("*").is_some() {
+        let flags = int("flg").unwrap_or(0);
+        return Ok(Object::ChoicePoint(Rc::new(ChoicePoint {
+            path_on_choice: path("*")?,
+            has_condition: flags & 0x1 != 0,
+            has_start_content: flags & 0x2 != 0,
+            has_choice_only_content: flags & 0x4 != 0,
+            is_invisible_default: flags & 0x8 != 0,
+            once_only: flags & 0x10 != 0,
+        })));
+    }
+
+    if get("VAR?").is_some() {
+        return Ok(Object::VariableReference(Rc::new(VariableReference::Variable(string("VAR?")?))));
+    }
+
+    if get("CNT?").is_some() {
+        return Ok(Object::VariableReference(Rc::new(VariableReference::PathForCount(path("CNT?")?))));
+    }
+
+    let assignment = [("VAR=", true), ("temp=", false)]
+        .iter()
+        .find(|(key, _)| get(key).is_some());
+    if let Some((key, is_global)) = assignment {
+        return Ok(Object::VariableAssignment(Rc::new(VariableAssignment {
+            variable_name: string(key)?,
+            is_new_declaration: get("re").is_none(),
+            is_global: *is_global,
+        })));
+    }
+
+    if get("#").is_some() {
+        return Ok(Object::Tag(Rc::new(Tag::new(string("#")?))));
+    }
+
+    if let Some(Token::Object(items)) = get("list") {
+        let mut list = List::default();
+        for (name, value) in items {
+            let (origin, item) = match name.find('.') {
+                Some(index) => (&name[..index], &name[index + 1..]),
+                None => return Err(serde_json::Error::custom(format!("Expected list item {} to be qualified with the name of its list", name))),
+            };
+            let value = match value {
+                Token::Int(value) => *value,
+                token => return Err(serde_json::Error::custom(format!("Expected the value of list item {} to be an int, found {}", name, token))),
+            };
+            list.origins.insert(origin.to_owned());
+            list.items.insert(ListEntry::new(origin.to_owned(), item.to_owned(), value));
+        }
+        if let Some(Token::Array(origins)) = get("origins") {
+            for origin in origins {
+                if let Token::String(origin) = origin {
+                    list.origins.insert(origin.clone());
+                }
+            }
+        }
+        return Ok(Object::Value(Value::List(list)));
+    }
+
+    let keys: Vec<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+    Err(serde_json::Error::custom(format!("Failed to convert object {{{}}} to a runtime object", keys.join(", "))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORY: &str = r##"{"inkVersion":19,"root":[["^Hello","\n",{"->":"knot"},["done",{"#n":"g-0"}],null],"done",{"knot":["^In the knot",{"#":"mood: calm"},"\n",{"stitch":["end",{"#f":3}]}],"global decl":["ev",5,{"VAR=":"x"},"/ev","end",null],"#f":1}],"listDefs":{"Colours":{"red":1,"blue":2}}}"##;
+
+    #[test]
+    fn loads_content() {
+        let content = load(STORY).unwrap();
+        let root = &content.main_container;
+        assert_eq!(root.content.len(), 2);
+        assert!(root.visits_should_be_counted);
+        let names: Vec<&str> = root.named_only_content.iter().map(|child| child.name()).collect();
+        assert_eq!(names, vec!["knot", "global decl"]);
+
+        let stitch = match root.content_at_path(&"knot.stitch".parse().unwrap()) {
+            Some(Object::Container(stitch)) => stitch,
+            object => panic!("expected a container, found {:?}", object),
+        };
+        assert!(stitch.visits_should_be_counted && stitch.turn_index_should_be_counted);
+        assert_eq!(Object::Container(stitch).path().to_string(), "knot.stitch");
+        assert_eq!(root.content_at_path(&"0.g-0".parse().unwrap()).map(|object| object.path().to_string()), Some("0.g-0".to_owned()));
+        match root.content_at_path(&"knot.1".parse().unwrap()) {
+            Some(Object::Tag(tag)) => assert_eq!(tag.text(), "mood: calm"),
+            object => panic!("expected a tag, found {:?}", object),
+        }
+
+        let colours = content.list_definitions.list_definition_by_name("Colours").unwrap();
+        assert_eq!(colours.item_named("blue").map(ListEntry::value), Some(2));
+    }
+
+    #[test]
+    fn loads_objects() {
+        let root = load(r##"{"inkVersion":19,"root":[[{"->":".^.c-0","c":true},{"f()":"fn"},{"->t->":"t","var":true},{"x()":"ext","exArgs":2},{"*":".^.c-0","flg":18},{"CNT?":".^"},{"temp=":"y","re":true},{"^var":"y","ci":2},{"list":{"Colours.red":1},"origins":["Colours"]},"L^",1.5,null],null]}"##)
+            .unwrap()
+            .main_container;
+        let objects: Vec<String> = match &root.content[0] {
+            Object::Container(container) => container.content.iter().map(ToString::to_string).collect(),
+            object => panic!("expected a container, found {:?}", object),
+        };
+        assert_eq!(objects, vec![
+            "divert -> .^.c-0 (conditional)",
+            "call -> fn",
+            "tunnel -> {t}",
+            "external ext(2 args)",
+            "choice point -> .^.c-0",
+            "read count .^",
+            "y =",
+            "ref y",
+            "list (red)",
+            "Intersect",
+            "1.5",
+        ]);
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        assert!(load(r##"{"inkVersion":21,"root":[null]}"##).is_err());
+        assert!(load(r##"{"inkVersion":17,"root":[null]}"##).is_err());
+        assert!(load(r##"{"root":[null]}"##).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::{self, Display, Formatter};
+use super::Path;
+
+/// Limits on the work a story may do, so that mistakes such as a `-> loop` with no choice or
+/// unbounded recursion fail with a `LimitExceeded` error instead of running forever. Every limit
+/// is disabled (`None`) by default.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Limits {
+    /// The maximum number of steps between one line of output (or choice) and the next, which is
+    /// the work done by a single `continue`.
+    pub max_steps_per_continue: Option<usize>,
+    /// The maximum number of frames in the call stack, caused by nested tunnels and functions.
+    pub max_call_stack_depth: Option<usize>,
+    pub max_evaluation_stack_size: Option<usize>,
+    /// The maximum number of objects in the output stream.
+    pub max_output_stream_length: Option<usize>,
+}
+
+/// The error a story fails with when it exceeds one of its `Limits`. Each case includes the path
+/// to the content that was being executed when the limit was exceeded.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum LimitExceeded {
+    Steps { limit: usize, path: Path },
+    CallStackDepth { limit: usize, path: Path },
+    EvaluationStackSize { limit: usize, path: Path },
+    OutputStreamLength { limit: usize, path: Path },
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LimitExceeded::Steps { limit, path } => write!(f, "Exceeded {} steps without producing output or a choice at {}. The story is probably stuck in a loop", limit, path),
+            LimitExceeded::CallStackDepth { limit, path } => write!(f, "Exceeded call stack depth of {} at {}. The story probably contains unbounded recursion", limit, path),
+            LimitExceeded::EvaluationStackSize { limit, path } => write!(f, "Exceeded evaluation stack size of {} at {}", limit, path),
+            LimitExceeded::OutputStreamLength { limit, path } => write!(f, "Exceeded output stream length of {} at {}", limit, path),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl Limits {
+    /// Finds the first limit which is exceeded by the given usage, if any.
+    pub(crate) fn check(&self, steps: usize, call_stack_depth: usize, evaluation_stack_size: usize, output_stream_length: usize, path: impl Fn() -> Path) -> Option<LimitExceeded> {
+        let exceeds = |limit: Option<usize>, value: usize| limit.filter(|&limit| value > limit);
+        if let Some(limit) = exceeds(self.max_steps_per_continue, steps) {
+            return Some(LimitExceeded::Steps { limit, path: path() });
+        }
+        if let Some(limit) = exceeds(self.max_call_stack_depth, call_stack_depth) {
+            return Some(LimitExceeded::CallStackDepth { limit, path: path() });
+        }
+        if let Some(limit) = exceeds(self.max_evaluation_stack_size, evaluation_stack_size) {
+            return Some(LimitExceeded::EvaluationStackSize { limit, path: path() });
+        }
+        if let Some(limit) = exceeds(self.max_output_stream_length, output_stream_length) {
+            return Some(LimitExceeded::OutputStreamLength { limit, path: path() });
+        }
+        None
+    }
+}
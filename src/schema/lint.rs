@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+use std::fmt::{self, Display, Formatter};
+use super::{Component, Container, ControlCommand, DivertTarget, EdgeKind, ExternalFunction, ListDefinitions, Object, Path, StoryGraph, Value, VariableReference, WalkItem};
+
+/// A problem found in a compiled story by `lint`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Lint {
+    /// A knot which no divert, choice, tunnel, function call or divert target value leads to.
+    /// Knots which are only reached through variable diverts or are called from the game are
+    /// reported too.
+    UnreachableKnot { knot: String },
+    /// A divert whose target does not exist.
+    UnresolvedDivert { at: Path, target: Path },
+    /// A reference to a variable which is not declared as a global, as a temporary in the same knot,
+    /// or as a list item.
+    UndeclaredVariable { at: Path, name: String },
+    /// An external function with no binding registered using `ink_external!`, and no ink fallback.
+    UnboundExternal { at: Path, name: String },
+    /// A function which may run off the end of its content without reaching a `~ return`.
+    MissingReturn { function: Path },
+    /// A read count of a container which does not count its visits.
+    UncountedVisits { at: Path, target: Path },
+    /// A `TURNS_SINCE` of a container which does not count its turns.
+    UncountedTurns { at: Path, target: Path },
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Lint::UnreachableKnot { knot } => write!(f, "knot {} is never reached", knot),
+            Lint::UnresolvedDivert { at, target } => write!(f, "{}: divert target {} does not exist", at, target),
+            Lint::UndeclaredVariable { at, name } => write!(f, "{}: variable {} is never declared", at, name),
+            Lint::UnboundExternal { at, name } => write!(f, "{}: external function {} has no binding", at, name),
+            Lint::MissingReturn { function } => write!(f, "function {} can end without returning", function),
+            Lint::UncountedVisits { at, target } => write!(f, "{}: read count of {}, which does not count visits", at, target),
+            Lint::UncountedTurns { at, target } => write!(f, "{}: turns since {}, which does not count turns", at, target),
+        }
+    }
+}
+
+/// Checks a compiled story for likely mistakes, returning every problem found.
+///
+/// Some of these checks are heuristics, as ink allows some behaviour (such as variable diverts)
+/// which cannot be checked without running the story.
+pub fn lint(root: &Container, lists: &ListDefinitions) -> Vec<Lint> {
+    let mut linter = Linter {
+        root,
+        lists,
+        globals: HashSet::new(),
+        temporaries: HashMap::new(),
+        externals: inventory::iter::<ExternalFunction>
+            .into_iter()
+            .map(|external| external.name)
+            .collect(),
+        lints: vec![],
+    };
+    linter.collect_declarations();
+    linter.check_content();
+    linter.check_reachability();
+    linter.lints
+}
+
+struct Linter<'a> {
+    root: &'a Container,
+    lists: &'a ListDefinitions,
+    globals: HashSet<String>,
+    /// The temporary variables declared in each knot
+    temporaries: HashMap<Option<String>, HashSet<String>>,
+    externals: HashSet<&'static str>,
+    lints: Vec<Lint>,
+}
+
+/// The name of the knot that the content at the path is in, if any.
+fn knot_of(path: &Path) -> Option<String> {
+    match path.parts.first() {
+        Some(Component::Name(name)) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Calls `f` for each non-container object in the container and its descendants, along with the
+/// object's path and the object before it in the same container.
+fn walk<'a, F>(container: &'a Container, f: &mut F) where F: FnMut(&Path, &'a Object, Option<&'a Object>) {
+    container.walk(&mut |path, item| {
+        if let WalkItem::Object(object, previous) = item {
+            f(path, object, previous);
+        }
+    });
+}
+
+impl<'a> Linter<'a> {
+    fn collect_declarations(&mut self) {
+        let globals = &mut self.globals;
+        let temporaries = &mut self.temporaries;
+        walk(self.root, &mut |path, object, _| {
+            if let Object::VariableAssignment(assignment) = object {
+                if !assignment.is_new_declaration { return }
+                if assignment.is_global {
+                    globals.insert(assignment.variable_name.clone());
+                } else {
+                    temporaries
+                        .entry(knot_of(path))
+                        .or_default()
+                        .insert(assignment.variable_name.clone());
+                }
+            }
+        });
+    }
+
+    fn is_declared(&self, name: &str, at: &Path) -> bool {
+        self.globals.contains(name)
+            || self.temporaries.get(&knot_of(at)).map(|temps| temps.contains(name)).unwrap_or(false)
+            || self.lists.find_list_entry(name).is_some()
+            || self.lists.list_definition_by_name(name).is_some()
+    }
+
+    fn container_at(&self, path: &Path) -> Option<Rc<Container>> {
+        match self.root.content_at_path(path)? {
+            Object::Container(container) => Some(container),
+            _ => None,
+        }
+    }
+
+    fn check_content(&mut self) {
+        let mut lints = vec![];
+        walk(self.root, &mut |at, object, previous| {
+            match object {
+                Object::Divert(divert) => match &divert.target {
+                    DivertTarget::Path(target) => {
+                        let target = at.appending(target);
+                        if self.root.content_at_path(&target).is_none() {
+                            lints.push(Lint::UnresolvedDivert { at: at.clone(), target });
+                        }
+                    }
+                    DivertTarget::Variable(name) => {
+                        if !self.is_declared(name, at) {
+                            lints.push(Lint::UndeclaredVariable { at: at.clone(), name: name.clone() });
+                        }
+                    }
+                    DivertTarget::External { path: name, .. } => {
+                        let mut fallback = Path::default();
+                        fallback.join(name.clone());
+                        if !self.externals.contains(name.as_str()) && self.root.content_at_path(&fallback).is_none() {
+                            lints.push(Lint::UnboundExternal { at: at.clone(), name: name.clone() });
+                        }
+                    }
+                },
+                Object::VariableReference(reference) => match &**reference {
+                    VariableReference::Variable(name) => {
+                        if !self.is_declared(name, at) {
+                            lints.push(Lint::UndeclaredVariable { at: at.clone(), name: name.clone() });
+                        }
+                    }
+                    VariableReference::PathForCount(target) => {
+                        let target = at.appending(target);
+                        if let Some(container) = self.container_at(&target) {
+                            if !container.visits_should_be_counted {
+                                lints.push(Lint::UncountedVisits { at: at.clone(), target });
+                            }
+                        }
+                    }
+                },
+                Object::ControlCommand(command @ ControlCommand::ReadCount)
+                | Object::ControlCommand(command @ ControlCommand::TurnsSince) => {
+                    let target = match previous {
+                        Some(Object::Value(Value::DivertTarget(target))) => at.appending(target),
+                        _ => return,
+                    };
+                    if let Some(container) = self.container_at(&target) {
+                        if *command == ControlCommand::ReadCount && !container.visits_should_be_counted {
+                            lints.push(Lint::UncountedVisits { at: at.clone(), target });
+                        } else if *command == ControlCommand::TurnsSince && !container.turn_index_should_be_counted {
+                            lints.push(Lint::UncountedTurns { at: at.clone(), target });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+        self.lints.extend(lints);
+    }
+
+    fn check_reachability(&mut self) {
+        let graph = StoryGraph::build(self.root);
+
+        // divert target values (e.g. `-> knot` passed to a function) may be diverted to later
+        let mut reached = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(Path::default());
+        walk(self.root, &mut |at, object, _| {
+            if let Object::Value(Value::DivertTarget(target)) = object {
+                queue.push_back(graph.node_containing(&at.appending(target)));
+            }
+        });
+        while let Some(node) = queue.pop_front() {
+            if !reached.insert(node.clone()) { continue }
+            for edge in graph.edges.iter().filter(|edge| edge.from == node) {
+                queue.push_back(edge.to.clone());
+            }
+        }
+
+        for knot in &graph.knots {
+            if !reached.iter().any(|node| knot_of(node).as_ref() == Some(&knot.name)) {
+                self.lints.push(Lint::UnreachableKnot { knot: knot.name.clone() });
+            }
+        }
+
+        let functions: HashSet<Path> = graph.edges
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Function)
+            .map(|edge| edge.to.clone())
+            .collect();
+        for function in functions {
+            if let Some(container) = self.container_at(&function) {
+                if !ends_with_return(&container) {
+                    self.lints.push(Lint::MissingReturn { function });
+                }
+            }
+        }
+    }
+}
+
+/// Whether the last object in the container leaves the function (returning, diverting away, or
+/// ending the story). If the last object is a container, it is checked instead.
+fn ends_with_return(container: &Container) -> bool {
+    match container.content.last() {
+        Some(Object::ControlCommand(ControlCommand::PopFunction))
+        | Some(Object::ControlCommand(ControlCommand::PopTunnel))
+        | Some(Object::ControlCommand(ControlCommand::Done))
+        | Some(Object::ControlCommand(ControlCommand::End)) => true,
+        Some(Object::Divert(divert)) => !divert.is_conditional && !divert.pushes_to_stack,
+        Some(Object::Container(child)) => ends_with_return(child),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Story;
+
+    fn lint_story(root: &str) -> Vec<Lint> {
+        Story::from_json(&format!(r#"{{"inkVersion":19,"root":{}}}"#, root)).unwrap().lint()
+    }
+
+    fn path(path: &str) -> Path {
+        path.parse().unwrap()
+    }
+
+    #[test]
+    fn knots_which_are_never_diverted_to_are_unreachable() {
+        let lints = lint_story(r#"[["^Hi","\n","end",null],"done",{"lost":["^Lost","\n","end",null]}]"#);
+        assert_eq!(lints, vec![Lint::UnreachableKnot { knot: "lost".to_owned() }]);
+    }
+
+    #[test]
+    fn diverts_to_missing_content_are_unresolved() {
+        let lints = lint_story(r#"[[{"->":"nowhere"},null],"done",null]"#);
+        assert_eq!(lints, vec![Lint::UnresolvedDivert { at: path("0.0"), target: path("nowhere") }]);
+    }
+
+    #[test]
+    fn variables_must_be_declared() {
+        let lints = lint_story(r#"[["ev",{"VAR?":"x"},"out","/ev","\n","end",null],"done",null]"#);
+        assert_eq!(lints, vec![Lint::UndeclaredVariable { at: path("0.1"), name: "x".to_owned() }]);
+    }
+
+    #[test]
+    fn externals_need_a_binding_or_fallback() {
+        let lints = lint_story(r#"[["ev",{"x()":"missing","exArgs":0},"out","/ev","\n","end",null],"done",null]"#);
+        assert_eq!(lints, vec![Lint::UnboundExternal { at: path("0.1"), name: "missing".to_owned() }]);
+    }
+
+    #[test]
+    fn functions_must_return() {
+        let lints = lint_story(r#"[["ev",{"f()":"f"},"pop","/ev","end",null],"done",{"f":["^F",null]}]"#);
+        assert_eq!(lints, vec![Lint::MissingReturn { function: path("f") }]);
+    }
+
+    #[test]
+    fn functions_ending_in_a_return_are_fine() {
+        let lints = lint_story(r#"[["ev",{"f()":"f"},"pop","/ev","end",null],"done",{"f":["ev",1,"/ev","~ret",null]}]"#);
+        assert_eq!(lints, vec![]);
+    }
+
+    #[test]
+    fn read_counts_need_counted_visits() {
+        let lints = lint_story(r#"[["ev",{"CNT?":"k"},"out","/ev","\n",{"->":"k"},null],"done",{"k":["end",null]}]"#);
+        assert_eq!(lints, vec![Lint::UncountedVisits { at: path("0.1"), target: path("k") }]);
+    }
+
+    #[test]
+    fn turns_since_needs_counted_turns() {
+        let lints = lint_story(r#"[["ev",{"^->":"k"},"turns","out","/ev","\n",{"->":"k"},null],"done",{"k":["end",null]}]"#);
+        assert_eq!(lints, vec![Lint::UncountedTurns { at: path("0.2"), target: path("k") }]);
+    }
+}
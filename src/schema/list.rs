@@ -2,9 +2,11 @@ use std::iter::FromIterator;
 use std::cmp::{Ord, PartialOrd, Ordering};
 use std::ops::{BitAnd, BitOr, Sub};
 use std::collections::BTreeSet;
+use serde::{Serialize, Deserialize};
 use super::{Value, ListDefinitions};
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+/// An item of an ink list. Serializes as `{"origin": "Inventory", "name": "sword", "value": 1}`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ListEntry {
     pub(crate) origin: String,
     pub(crate) name: String,
@@ -13,7 +15,28 @@ pub struct ListEntry {
 
 impl Ord for ListEntry {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.value.cmp(&other.value).then(self.name.cmp(&other.name))
+        self.value.cmp(&other.value)
+            .then_with(|| self.origin.cmp(&other.origin))
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl ListEntry {
+    pub fn new(origin: String, name: String, value: i64) -> Self {
+        Self { origin, name, value }
+    }
+
+    /// The name of the list this item is defined in.
+    pub fn origin(&self) -> &str { &self.origin }
+
+    /// The name of this item, without the name of the list.
+    pub fn name(&self) -> &str { &self.name }
+
+    pub fn value(&self) -> i64 { self.value }
+
+    /// The name of this item, qualified with the name of the list (e.g. `Inventory.sword`).
+    pub fn full_name(&self) -> String {
+        format!("{}.{}", self.origin, self.name)
     }
 }
 
@@ -30,14 +53,29 @@ pub struct ListDefinition {
 }
 
 impl ListDefinition {
-    pub(crate) fn item_with_value(&self, value: i64) -> Option<&ListEntry> {
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Iterates over the items of this list, in order of their values.
+    pub fn items(&self) -> impl Iterator<Item = &ListEntry> {
+        self.items.iter()
+    }
+
+    pub fn item_with_value(&self, value: i64) -> Option<&ListEntry> {
         self.items
             .iter()
             .find(|entry| entry.value == value)
     }
+
+    pub fn item_named(&self, name: &str) -> Option<&ListEntry> {
+        self.items
+            .iter()
+            .find(|entry| entry.name == name)
+    }
 }
 
-#[derive(Clone, Debug, Default)]
+/// A value of an ink list. Serializes as `{"origins": [...], "items": [...]}`, where the items are
+/// `ListEntry`s, in order of their values.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct List {
     pub(crate) origins: BTreeSet<String>,
     pub(crate) items: BTreeSet<ListEntry>,
@@ -59,6 +97,17 @@ impl List {
         }
     }
 
+    /// Builds a list from the names of its items, which are resolved using the list definitions.
+    /// Names may be qualified with the name of their list (`Inventory.sword`), or unqualified
+    /// (`sword`) as long as only one list defines an item with that name.
+    ///
+    /// Returns `None` if any of the names cannot be resolved.
+    pub fn from_item_names<'a, I: IntoIterator<Item = &'a str>>(names: I, lists: &ListDefinitions) -> Option<Self> {
+        names.into_iter()
+            .map(|name| lists.find_list_entry(name).cloned())
+            .collect()
+    }
+
     pub(crate) fn with_empty_origins<'a, I: IntoIterator<Item = &'a String>>(mut self, origins: I) -> Self {
         if self.items.is_empty() {
             self.origins = origins.into_iter().cloned().collect();
@@ -69,13 +118,40 @@ impl List {
 
 // Accessors
 impl List {
-    pub(crate) fn len(&self) -> usize { self.items.len() }
-    pub(crate) fn is_empty(&self) -> bool { self.items.is_empty() }
+    pub fn len(&self) -> usize { self.items.len() }
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+
+    /// Iterates over the items in this list, in order of their values.
+    pub fn iter(&self) -> impl Iterator<Item = &ListEntry> {
+        self.items.iter()
+    }
+
+    /// The names of the lists that this list's items may come from. This may include lists that
+    /// none of the items are from, as ink keeps track of the types of empty lists.
+    pub fn origins(&self) -> impl Iterator<Item = &str> {
+        self.origins.iter().map(String::as_str)
+    }
+
+    pub fn contains_entry(&self, entry: &ListEntry) -> bool {
+        self.items.contains(entry)
+    }
+
+    /// Checks whether the list contains an item with the given name, which may be qualified with
+    /// the name of its list (`Inventory.sword`) or not (`sword`).
+    pub fn contains_item_named(&self, name: &str) -> bool {
+        self.items
+            .iter()
+            .any(|entry| entry.name == name || entry.full_name() == name)
+    }
 }
 
 // Operations
 impl List {
+    /// The items of this list with values between `min` and `max` (inclusive). Each bound may be an
+    /// Int, or a List in which case its minimum or maximum item's value (respectively) is used.
     pub(crate) fn slice(&self, min: Value, max: Value) -> Self {
+        if self.is_empty() { return List::default() }
+
         let min = match min {
             Value::Int(v) => v,
             Value::List(list) => list.min().map(|v| v.value).unwrap_or(0),
@@ -83,8 +159,8 @@ impl List {
         };
         let max = match max {
             Value::Int(v) => v,
-            Value::List(list) => list.min().map(|v| v.value).unwrap_or(i64::max_value()),
-            _ => i64::max_value(),
+            Value::List(list) => list.max().map(|v| v.value).unwrap_or(i64::MAX),
+            _ => i64::MAX,
         };
 
         List {
@@ -97,48 +173,44 @@ impl List {
         }
     }
 
-    pub(crate) fn min(&self) -> Option<&ListEntry> {
-        self.items
-            .iter()
-            .fold(None, |min, cur| match (min, cur) {
-                (None, entry) => Some(entry),
-                (Some(ListEntry { value: a, .. }), ListEntry { value: b, .. }) if b < a => Some(cur),
-                _ => min,
-            })
+    /// The item with the lowest value in this list.
+    pub fn min(&self) -> Option<&ListEntry> {
+        self.items.iter().next()
     }
 
-    pub(crate) fn max(&self) -> Option<&ListEntry> {
-        self.items
-            .iter()
-            .fold(None, |max, cur| match (max, cur) {
-                (None, entry) => Some(entry),
-                (Some(ListEntry { value: a, .. }), ListEntry { value: b, .. }) if b > a => Some(cur),
-                _ => max,
-            })
+    /// The item with the highest value in this list.
+    pub fn max(&self) -> Option<&ListEntry> {
+        self.items.iter().next_back()
     }
 
+    /// Whether every item in this list has a greater value than every item in the other list.
     pub fn gt(&self, other: &Self) -> bool {
         if self.is_empty() { return false }
         if other.is_empty() { return true }
-        self.min().unwrap() > other.max().unwrap()
+        self.min().unwrap().value > other.max().unwrap().value
     }
 
+    /// Whether every item in this list has a lower value than every item in the other list.
     pub fn lt(&self, other: &Self) -> bool {
         if other.is_empty() { return false }
         if self.is_empty() { return true }
-        self.max().unwrap() < other.min().unwrap()
+        self.max().unwrap().value < other.min().unwrap().value
     }
 
+    /// Whether both the minimum and maximum values of this list are greater than or equal to those
+    /// of the other list.
     pub fn ge(&self, other: &Self) -> bool {
         if self.is_empty() { return false }
         if other.is_empty() { return true }
-        self.max().unwrap() >= other.max().unwrap() && self.min().unwrap() >= other.min().unwrap()
+        self.min().unwrap().value >= other.min().unwrap().value && self.max().unwrap().value >= other.max().unwrap().value
     }
 
+    /// Whether both the minimum and maximum values of this list are less than or equal to those
+    /// of the other list.
     pub fn le(&self, other: &Self) -> bool {
         if other.is_empty() { return false }
         if self.is_empty() { return true }
-        self.max().unwrap() <= other.max().unwrap() && self.min().unwrap() <= other.min().unwrap()
+        self.max().unwrap().value <= other.max().unwrap().value && self.min().unwrap().value <= other.min().unwrap().value
     }
 
     pub fn increment(&self, distance: i64, lists: &ListDefinitions) -> Self {
@@ -155,7 +227,10 @@ impl List {
         }
     }
 
+    /// Whether this list contains all the items of the other list. As in ink, an empty list neither
+    /// contains nor is contained by any other list.
     pub fn contains(&self, other: &Self) -> bool {
+        if self.is_empty() || other.is_empty() { return false }
         for item in &other.items {
             if !self.items.contains(item) {
                 return false
@@ -213,6 +288,14 @@ impl PartialEq for List {
     }
 }
 
+impl<'a> IntoIterator for &'a List {
+    type Item = &'a ListEntry;
+    type IntoIter = std::collections::btree_set::Iter<'a, ListEntry>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
 impl FromIterator<ListEntry> for List {
     fn from_iter<I: IntoIterator<Item=ListEntry>>(iter: I) -> Self {
         let items: BTreeSet<ListEntry> = iter.into_iter().collect();
@@ -222,3 +305,67 @@ impl FromIterator<ListEntry> for List {
         }
     } 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colours() -> ListDefinitions {
+        let items = ["red", "green", "blue"]
+            .iter()
+            .zip(1..)
+            .map(|(name, value)| ListEntry::new("Colours".to_owned(), name.to_string(), value))
+            .collect();
+        ListDefinitions::new(vec![ListDefinition { name: "Colours".to_owned(), items }])
+    }
+
+    fn list(names: &[&str]) -> List {
+        List::from_item_names(names.iter().cloned(), &colours()).unwrap()
+    }
+
+    #[test]
+    fn slice_bounds() {
+        let all = list(&["red", "green", "blue"]);
+        assert_eq!(all.slice(Value::Int(2), Value::Int(3)), list(&["green", "blue"]));
+        // a list as the minimum bound uses its minimum, and as the maximum bound uses its maximum
+        assert_eq!(all.slice(Value::List(list(&["green", "blue"])), Value::List(list(&["red", "green"]))), list(&["green"]));
+        // empty bounds do not limit the range
+        assert_eq!(all.slice(Value::List(list(&[])), Value::List(list(&[]))), all);
+        assert_eq!(list(&[]).slice(Value::Int(0), Value::Int(5)), list(&[]));
+    }
+
+    #[test]
+    fn ge_and_le() {
+        assert!(list(&["green", "blue"]).ge(&list(&["red", "green"])));
+        assert!(list(&["green"]).ge(&list(&["green"])));
+        assert!(!list(&["red", "blue"]).ge(&list(&["green"])));
+        assert!(list(&["red"]).ge(&list(&[])));
+        assert!(!list(&[]).ge(&list(&["red"])));
+
+        assert!(list(&["red", "green"]).le(&list(&["green", "blue"])));
+        assert!(!list(&["red", "blue"]).le(&list(&["green"])));
+        assert!(list(&[]).le(&list(&["red"])));
+        assert!(!list(&["red"]).le(&list(&[])));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let entry = ListEntry::new("Colours".to_owned(), "red".to_owned(), 1);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"origin":"Colours","name":"red","value":1}"#);
+        assert_eq!(serde_json::from_str::<ListEntry>(&json).unwrap(), entry);
+
+        let list = list(&["red", "blue"]);
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(serde_json::from_str::<List>(&json).unwrap(), list);
+    }
+
+    #[test]
+    fn contains_with_empty_lists() {
+        assert!(list(&["red", "green"]).contains(&list(&["red"])));
+        assert!(!list(&["red"]).contains(&list(&["red", "green"])));
+        assert!(!list(&["red"]).contains(&list(&[])));
+        assert!(!list(&[]).contains(&list(&["red"])));
+        assert!(!list(&[]).contains(&list(&[])));
+    }
+}
@@ -26,14 +26,35 @@ impl ListDefinitions {
         }
     }
 
-    pub(crate) fn list_definition_by_name(&self, name: &String) -> Option<&ListDefinition> {
+    pub fn list_definition_by_name(&self, name: &str) -> Option<&ListDefinition> {
         self.list_definitions.get(name)
     }
-    
-    pub(crate) fn lookup_list_entry(&self, name: &String) -> Option<&ListEntry> {
+
+    /// Looks up a list item by its qualified name (e.g. `Inventory.sword`).
+    pub fn lookup_list_entry(&self, name: &str) -> Option<&ListEntry> {
         self.list_entry_lookup_cache.get(name)
     }
 
+    /// Looks up a list item by its name, which may be qualified (`Inventory.sword`) or not (`sword`).
+    /// An unqualified name is only found if exactly one list defines an item with that name.
+    pub fn find_list_entry(&self, name: &str) -> Option<&ListEntry> {
+        if let Some(entry) = self.lookup_list_entry(name) {
+            return Some(entry);
+        }
+        let mut candidates = self.list_definitions
+            .values()
+            .filter_map(|def| def.item_named(name));
+        match (candidates.next(), candidates.next()) {
+            (Some(entry), None) => Some(entry),
+            _ => None,
+        }
+    }
+
+    /// Iterates over all of the list definitions, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &ListDefinition> {
+        self.list_definitions.values()
+    }
+
     pub(crate) fn all_from_origins<'a, I: IntoIterator<Item = &'a String>>(&self, origins: I) -> List {
         origins.into_iter()
             .filter_map(|origin| self.list_definition_by_name(origin))
@@ -7,22 +7,32 @@ pub trait TryAsRef<T> where T: ?Sized {
 mod list;
 mod object;
 mod path;
+mod playtest;
 mod pointer;
 mod push_pop_type;
+mod rng;
 mod value;
+mod value_formatter;
 #[macro_use]
 mod external_function;
+mod limits;
 mod list_definitions;
+mod numeric_mode;
 mod variable_context;
 
 pub use list::{ListEntry, List, ListDefinition};
 pub use list_definitions::ListDefinitions;
-pub use object::Object;
+pub use numeric_mode::NumericMode;
+pub use object::{Object, ObjectKind};
 pub(crate) use object::NamedObject;
-pub use path::{Path, Component};
+pub(crate) use container::WalkItem;
+pub(crate) use save_state::{SaveState, ThreadState, ElementState, OutputState, ChoiceState};
+pub use path::{Path, Component, ParsePathError};
 pub use pointer::Pointer;
 pub use push_pop_type::PushPopType;
+pub use rng::{InkRng, PcgRng, DotNetRandom};
 pub use value::{Value, VariablePointer};
+pub use value_formatter::{ValueFormatter, CanonicalValueFormatter};
 pub use external_function::ExternalFunction;
 pub use variable_context::VariableContext;
 
@@ -30,11 +40,25 @@ mod choice;
 mod choice_point;
 mod container;
 mod control_command;
+mod coverage;
+mod debugger;
+mod disassembler;
 mod divert;
 mod glue;
+mod ink_json;
+mod lint;
 mod native_function_call;
+mod output_sink;
+mod profiler;
+mod protocol;
+mod save_state;
 mod story;
+mod story_error;
+mod story_graph;
+mod string_table;
 mod tag;
+mod tag_parser;
+mod trace;
 mod variable_assignment;
 mod variable_reference;
 
@@ -42,10 +66,27 @@ pub use choice::Choice;
 pub use choice_point::ChoicePoint;
 pub use container::Container;
 pub use control_command::ControlCommand;
+pub use coverage::{Coverage, CoverageReport, ContainerCoverage, UnvisitedLine};
+pub use debugger::{Breakpoint, Debugger, DebugStop};
+pub use disassembler::Disassembly;
 pub use divert::{Divert, DivertTarget};
 pub use glue::Glue;
+pub use lint::{lint, Lint};
 pub use native_function_call::NativeFunctionCall;
+pub use output_sink::OutputSink;
+pub(crate) use output_sink::OutputSinkWriter;
+pub use limits::{Limits, LimitExceeded};
+pub use protocol::{Request, Response, respond, serve};
+pub use playtest::{Playtester, PlaytestReport, PlaytestFailure, FailureKind};
+pub use profiler::{Profiler, ProfileEntry, CallTreeNode};
 pub use story::{Story, Thread, Element};
+pub use story_error::StoryError;
+pub(crate) use story_error::panic_message;
+pub use story_graph::{StoryGraph, Knot, Edge, EdgeKind};
+pub use string_table::StringTable;
 pub use tag::Tag;
+pub use tag_parser::{TagParser, TagValue};
+pub use trace::{TraceEvent, Tracer, TraceWriter};
+pub(crate) use trace::PendingTrace;
 pub use variable_assignment::VariableAssignment;
 pub use variable_reference::VariableReference;
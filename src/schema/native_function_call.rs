@@ -1,5 +1,5 @@
 use std::convert::TryInto;
-use super::{List, ListDefinitions, Value};
+use super::{List, ListDefinitions, NumericMode, Value, ValueFormatter};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum NativeFunctionCall {
@@ -43,20 +43,34 @@ pub enum NativeFunctionCall {
 }
 
 impl NativeFunctionCall {
-    fn coerce(mut params: Vec<Value>) -> (Value, Value) {
-        let a = params.pop().unwrap();
+    /// Coerces the two parameters of a binary operation to the same type. The parameters are in
+    /// the order they were pushed to the evaluation stack, so the first is the left-hand side.
+    /// Values are coerced to strings by the formatter, so they read the same as when printed.
+    fn coerce(lists: &ListDefinitions, mode: NumericMode, formatter: &dyn ValueFormatter, mut params: Vec<Value>) -> (Value, Value) {
         let b = params.pop().unwrap();
+        let a = params.pop().unwrap();
+        let (a, b) = Self::coerce_values(lists, formatter, a, b);
+        (mode.normalize(a), mode.normalize(b))
+    }
+
+    fn coerce_values(lists: &ListDefinitions, formatter: &dyn ValueFormatter, a: Value, b: Value) -> (Value, Value) {
         match (a, b) {
             // fancy types cannot coerce normally
             | (Value::VariablePointer(..), _)
             | (_, Value::VariablePointer(..)) => panic!("Variables should be resolved before calling a function"),
             (Value::DivertTarget(a), b) => (Value::DivertTarget(a), b),
             (a, Value::DivertTarget(b)) => (a, Value::DivertTarget(b)),
+            // strings are highest priority
+            (Value::String(a), b) => (Value::String(a), Value::String(formatter.format(&b))),
+            (a, Value::String(b)) => (Value::String(formatter.format(&a)), Value::String(b)),
+            // then lists, which ints are coerced to by finding the item with that value
+            (Value::List(a), Value::Int(b)) => {
+                let b = Self::coerce_int_to_list(lists, &a, b);
+                (Value::List(a), Value::List(b))
+            }
+            (Value::Int(a), Value::List(b)) => (Value::List(Self::coerce_int_to_list(lists, &b, a)), Value::List(b)),
             (Value::List(a), b) => (Value::List(a), b),
             (a, Value::List(b)) => (a, Value::List(b)),
-            // strings are highest priority
-            (Value::String(a), b) => (Value::String(a), Value::String(b.try_into().unwrap())),
-            (a, Value::String(b)) => (Value::String(a.try_into().unwrap()), Value::String(b)),
             // then floats
             (Value::Float(a), b) => (Value::Float(a), Value::Float(b.try_into().unwrap())),
             (a, Value::Float(b)) => (Value::Float(a.try_into().unwrap()), Value::Float(b)),
@@ -66,6 +80,20 @@ impl NativeFunctionCall {
         }
     }
 
+    /// Converts an int to the item with that value from the list that the other list's maximum item
+    /// comes from.
+    fn coerce_int_to_list(lists: &ListDefinitions, list: &List, value: i64) -> List {
+        let origin = list.max()
+            .map(|entry| entry.origin.as_str())
+            .or_else(|| list.origins().next())
+            .unwrap_or_else(|| panic!("Cannot coerce the value {} to a list item when the other list has no origin", value));
+        let definition = lists.list_definition_by_name(origin)
+            .unwrap_or_else(|| panic!("No list definition found named {}", origin));
+        let entry = definition.item_with_value(value)
+            .unwrap_or_else(|| panic!("Could not find List item with the value {} in {}", value, origin));
+        List::of_single_value(entry.clone())
+    }
+
     pub fn number_of_parameters(&self) -> usize {
         use NativeFunctionCall::*;
         match self {
@@ -92,46 +120,59 @@ impl NativeFunctionCall {
         }
     }
 
-    pub(crate) fn call(&self, lists: &ListDefinitions, params: Vec<Value>) -> Value {
+    /// Calls this function with the given parameters, evaluating numbers according to the numeric
+    /// mode, and converting values to strings with the formatter.
+    pub(crate) fn call(&self, lists: &ListDefinitions, mode: NumericMode, formatter: &dyn ValueFormatter, params: Vec<Value>) -> Value {
+        let params = params
+            .into_iter()
+            .map(|param| mode.normalize(param))
+            .collect();
+        mode.normalize(self.evaluate(lists, mode, formatter, params))
+    }
+
+    fn evaluate(&self, lists: &ListDefinitions, mode: NumericMode, formatter: &dyn ValueFormatter, params: Vec<Value>) -> Value {
         use NativeFunctionCall::*;
         match self {
             Add => {
-                match Self::coerce(params) {
+                // adding an int to a list increments the items of the list, rather than coercing
+                if let [Value::List(a), Value::Int(b)] = params.as_slice() {
+                    return a.increment(*b, lists).into();
+                }
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a + b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a + b).into(),
                     (Value::String(a), Value::String(b)) => return (a + &b).into(),
                     (Value::List(a), Value::List(b)) => return (a | &b).into(),
-                    | (Value::List(a), Value::Int(b))
-                    | (Value::Int(b), Value::List(a)) => return a.increment(b, lists).into(),
                     _ => {},
                 }
             }
             Subtract => {
-                match Self::coerce(params) {
+                if let [Value::List(a), Value::Int(b)] = params.as_slice() {
+                    return a.increment(-b, lists).into();
+                }
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a - b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a - b).into(),
                     (Value::List(a), Value::List(b)) => return (a - &b).into(),
-                    | (Value::List(a), Value::Int(b))
-                    | (Value::Int(b), Value::List(a)) => return a.increment(-b, lists).into(),
                     _ => {},
                 }
             }
             Multiply => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a * b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a * b).into(),
                     _ => {},
                 }
             }
             Divide => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a / b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a / b).into(),
                     _ => {},
                 }
             }
             Mod => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a % b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a % b).into(),
                     _ => {},
@@ -145,7 +186,7 @@ impl NativeFunctionCall {
                 }
             }
             Equal => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a == b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a == b).into(),
                     (Value::String(a), Value::String(b)) => return (a == b).into(),
@@ -155,7 +196,7 @@ impl NativeFunctionCall {
                 }
             }
             NotEquals => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a != b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a != b).into(),
                     (Value::String(a), Value::String(b)) => return (a != b).into(),
@@ -165,7 +206,7 @@ impl NativeFunctionCall {
                 }
             }
             Greater => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a > b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a > b).into(),
                     (Value::String(a), Value::String(b)) => return (a > b).into(),
@@ -174,7 +215,7 @@ impl NativeFunctionCall {
                 }
             }
             Less => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a < b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a < b).into(),
                     (Value::String(a), Value::String(b)) => return (a < b).into(),
@@ -183,7 +224,7 @@ impl NativeFunctionCall {
                 }
             }
             GreaterThanOrEquals => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a >= b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a >= b).into(),
                     (Value::String(a), Value::String(b)) => return (a >= b).into(),
@@ -192,7 +233,7 @@ impl NativeFunctionCall {
                 }
             }
             LessThanOrEquals => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a <= b).into(),
                     (Value::Float(a), Value::Float(b)) => return (a <= b).into(),
                     (Value::String(a), Value::String(b)) => return (a <= b).into(),
@@ -216,21 +257,21 @@ impl NativeFunctionCall {
                 return (params[0].is_truthy() || params[1].is_truthy()).into()
             }
             Max => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return i64::max(a, b).into(),
                     (Value::Float(a), Value::Float(b)) => return f64::max(a, b).into(),
                     _ => {},
                 }
             }
             Min => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return i64::min(a, b).into(),
                     (Value::Float(a), Value::Float(b)) => return f64::min(a, b).into(),
                     _ => {},
                 }
             }
             Pow => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::Int(a), Value::Int(b)) => return (a as f64).powf(b as f64).into(),
                     (Value::Float(a), Value::Float(b)) => return a.powf(b).into(),
                     _ => {},
@@ -253,7 +294,7 @@ impl NativeFunctionCall {
             Int => {
                 match &params[0] {
                     &Value::Int(a) => return a.into(),
-                    &Value::Float(a) => return (a as i64).into(),
+                    &Value::Float(a) => return mode.float_to_int(a).into(),
                     _ => {},
                 }
             }
@@ -265,14 +306,14 @@ impl NativeFunctionCall {
                 }
             }
             Has => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::String(a), Value::String(b)) => return a.contains(&b).into(),
                     (Value::List(a), Value::List(b)) => return a.contains(&b).into(),
                     _ => {},
                 }
             }
             Hasnt => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::String(a), Value::String(b)) => return (!a.contains(&b)).into(),
                     (Value::List(a), Value::List(b)) => return (!a.contains(&b)).into(),
                     _ => {},
@@ -285,7 +326,7 @@ impl NativeFunctionCall {
                 }
             }
             Intersect => {
-                match Self::coerce(params) {
+                match Self::coerce(lists, mode, formatter, params) {
                     (Value::List(a), Value::List(b)) => return (a & &b).into(),
                     _ => {},
                 }
@@ -312,7 +353,7 @@ impl NativeFunctionCall {
             }
             All => {
                 match &params[0] {
-                    Value::List(a) => return lists.all_from_origins(&a.origins).into(),
+                    Value::List(a) => return lists.all_from_origins(&a.origins).with_empty_origins(&a.origins).into(),
                     _ => {},
                 }
             }
@@ -324,7 +365,7 @@ impl NativeFunctionCall {
             }
             ValueOfList => {
                 match &params[0] {
-                    Value::List(a) => return a.max().unwrap().value.into(),
+                    Value::List(a) => return a.max().map(|entry| entry.value).unwrap_or(0).into(),
                     _ => {},
                 }
             }
@@ -332,3 +373,51 @@ impl NativeFunctionCall {
         panic!("Incompatible parameters passed to native function call: {:?}", self);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::CanonicalValueFormatter;
+
+    fn call(function: NativeFunctionCall, params: Vec<Value>) -> Value {
+        call_in_mode(NumericMode::default(), function, params)
+    }
+
+    fn call_in_mode(mode: NumericMode, function: NativeFunctionCall, params: Vec<Value>) -> Value {
+        function.call(&ListDefinitions::new(vec![]), mode, &CanonicalValueFormatter { numeric_mode: mode }, params)
+    }
+
+    #[test]
+    fn binary_operators_take_the_left_hand_side_first() {
+        assert_eq!(call(NativeFunctionCall::Subtract, vec![Value::Int(5), Value::Int(3)]), Value::Int(2));
+        assert_eq!(call(NativeFunctionCall::Divide, vec![Value::Int(6), Value::Int(3)]), Value::Int(2));
+        assert_eq!(call(NativeFunctionCall::Subtract, vec![Value::Int(1), Value::Float(0.25)]), Value::Float(0.75));
+        assert_eq!(call(NativeFunctionCall::Add, vec![Value::String("a".to_owned()), Value::Int(1)]), Value::String("a1".to_owned()));
+        assert_eq!(call(NativeFunctionCall::Add, vec![Value::Int(1), Value::String("a".to_owned())]), Value::String("1a".to_owned()));
+        assert_eq!(call(NativeFunctionCall::Less, vec![Value::Int(1), Value::Float(1.5)]), Value::Int(1));
+    }
+
+    #[test]
+    fn strings_are_concatenated_with_values_as_printed() {
+        let text = Value::String("x".to_owned());
+        assert_eq!(call(NativeFunctionCall::Add, vec![text.clone(), Value::Float(0.1)]), Value::String("x0.1".to_owned()));
+        assert_eq!(call_in_mode(NumericMode::CSharp, NativeFunctionCall::Add, vec![text.clone(), Value::Float(0.1)]), Value::String("x0.1".to_owned()));
+        assert_eq!(call_in_mode(NumericMode::CSharp, NativeFunctionCall::Add, vec![Value::Float(0.1), text]), Value::String("0.1x".to_owned()));
+    }
+
+    #[test]
+    fn has_on_strings() {
+        let text = Value::String("hello world".to_owned());
+        assert_eq!(call(NativeFunctionCall::Has, vec![text.clone(), Value::String("world".to_owned())]), Value::Int(1));
+        assert_eq!(call(NativeFunctionCall::Has, vec![text.clone(), Value::String("moon".to_owned())]), Value::Int(0));
+        assert_eq!(call(NativeFunctionCall::Hasnt, vec![text.clone(), Value::String("moon".to_owned())]), Value::Int(1));
+        assert_eq!(call(NativeFunctionCall::Has, vec![text, Value::Int(1)]), Value::Int(0));
+    }
+
+    #[test]
+    fn has_with_empty_lists() {
+        let empty = Value::List(List::default());
+        assert_eq!(call(NativeFunctionCall::Has, vec![empty.clone(), empty.clone()]), Value::Int(0));
+        assert_eq!(call(NativeFunctionCall::Hasnt, vec![empty.clone(), empty]), Value::Int(1));
+    }
+}
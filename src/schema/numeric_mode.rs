@@ -0,0 +1,56 @@
+use super::Value;
+
+/// How numbers behave when evaluated by a `Story`.
+///
+/// Values are always stored as `i64` and `f64`, but when emulating C# those values are kept within
+/// the range and precision of the 32-bit `int` and `float` types used by the official runtime.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum NumericMode {
+    /// 64-bit integers and floats.
+    #[default]
+    Native,
+    /// 32-bit integers (with wrapping arithmetic) and floats, as in the official C# runtime.
+    CSharp,
+}
+
+impl NumericMode {
+    /// Truncates an integer to the range of this mode, wrapping on overflow like unchecked C#.
+    pub(crate) fn int(self, value: i64) -> i64 {
+        match self {
+            NumericMode::Native => value,
+            NumericMode::CSharp => value as i32 as i64,
+        }
+    }
+
+    /// Rounds a float to the precision of this mode.
+    pub(crate) fn float(self, value: f64) -> f64 {
+        match self {
+            NumericMode::Native => value,
+            NumericMode::CSharp => value as f32 as f64,
+        }
+    }
+
+    /// Converts a float to an int, truncating towards zero. When emulating C#, values which cannot
+    /// be represented (including NaN) become `int.MinValue`, as they do in the official runtime.
+    pub(crate) fn float_to_int(self, value: f64) -> i64 {
+        match self {
+            NumericMode::Native => value as i64,
+            NumericMode::CSharp => {
+                if value.is_nan() || value <= i32::MIN as f64 - 1.0 || value >= i32::MAX as f64 + 1.0 {
+                    i32::MIN as i64
+                } else {
+                    value as i32 as i64
+                }
+            }
+        }
+    }
+
+    /// Brings an Int or Float value into the range and precision of this mode.
+    pub(crate) fn normalize(self, value: Value) -> Value {
+        match value {
+            Value::Int(value) => Value::Int(self.int(value)),
+            Value::Float(value) => Value::Float(self.float(value)),
+            value => value,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::convert::TryInto;
+use std::fmt::{self, Display, Formatter};
 use std::rc::Rc;
 use super::*;
 
@@ -8,8 +9,7 @@ pub(crate) trait NamedObject {
 }
 
 impl<T> NamedObject for Rc<T> where T: NamedObject {
-    #[allow(unconditional_recursion)] // its (supposed to be) calling a different implementation of this method...
-    fn name(&self) -> &str { (*self).name() }
+    fn name(&self) -> &str { (**self).name() }
 }
 
 #[derive(Clone, Debug)]
@@ -29,7 +29,43 @@ pub enum Object {
     Void,
 }
 
+/// The kind of an `Object`, without its content.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ObjectKind {
+    Choice,
+    ChoicePoint,
+    Container,
+    ControlCommand,
+    Divert,
+    Glue,
+    NativeFunctionCall,
+    Story,
+    Tag,
+    VariableAssignment,
+    VariableReference,
+    Value,
+    Void,
+}
+
 impl Object {
+    pub fn kind(&self) -> ObjectKind {
+        match self {
+            Object::Choice(..) => ObjectKind::Choice,
+            Object::ChoicePoint(..) => ObjectKind::ChoicePoint,
+            Object::Container(..) => ObjectKind::Container,
+            Object::ControlCommand(..) => ObjectKind::ControlCommand,
+            Object::Divert(..) => ObjectKind::Divert,
+            Object::Glue(..) => ObjectKind::Glue,
+            Object::NativeFunctionCall(..) => ObjectKind::NativeFunctionCall,
+            Object::Story(..) => ObjectKind::Story,
+            Object::Tag(..) => ObjectKind::Tag,
+            Object::VariableAssignment(..) => ObjectKind::VariableAssignment,
+            Object::VariableReference(..) => ObjectKind::VariableReference,
+            Object::Value(..) => ObjectKind::Value,
+            Object::Void => ObjectKind::Void,
+        }
+    }
+
     /// The path to this object from the root of the story
     pub(crate) fn path(&self) -> Path {
         let mut path = Path::default();
@@ -49,6 +85,8 @@ impl Object {
             maybe_container = content.parent();
         }
 
+        // the components were collected from the leaf up to the root
+        path.parts.reverse();
         path
     }
 
@@ -61,24 +99,6 @@ impl Object {
         }
     }
 
-    pub(crate) fn resolve_path(&self, path: &Path) -> Option<Self> {
-        if path.is_relative {
-            match path[0] {
-                Component::Parent => TryAsRef::<Rc<Container>>::try_as_ref(&self.parent()?)?.content_at_path_part(path, 1),
-                _ => TryAsRef::<Rc<Container>>::try_as_ref(self)?.content_at_path(path),
-            }
-        } else {
-            self.root_container()?.content_at_path(path)
-        }
-    }
-
-    pub(crate) fn root_container(&self) -> Option<Rc<Container>> {
-        match TryAsRef::<Rc<Container>>::try_as_ref(&self.parent()?) {
-            None => self.try_as_ref().cloned(),
-            Some(..) => self.parent().unwrap().root_container()
-        }
-    }
-
     /// Checks the truthiness of the Object. Returns false if the contained object is not a Value
     /// object. Otherwise, follows the values returned from `Value::is_truthy`:
     /// *   Int: value is not 0
@@ -98,6 +118,54 @@ impl Object {
     }
 }
 
+/// A short, single line description of the object, for debugging and tracing.
+impl Display for Object {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Object::Choice(choice) => write!(f, "choice {:?}", choice.text),
+            Object::ChoicePoint(choice_point) => write!(f, "choice point -> {}", choice_point.path_on_choice),
+            Object::Container(container) if container.has_valid_name() => write!(f, "container {}", container.name),
+            Object::Container(..) => write!(f, "container"),
+            Object::ControlCommand(command) => write!(f, "{:?}", command),
+            Object::Divert(divert) => {
+                let kind = match (divert.pushes_to_stack, divert.stack_push_type) {
+                    (false, _) => "divert",
+                    (true, PushPopType::Tunnel) => "tunnel",
+                    (true, _) => "call",
+                };
+                let condition = if divert.is_conditional { " (conditional)" } else { "" };
+                match &divert.target {
+                    DivertTarget::Path(path) => write!(f, "{} -> {}{}", kind, path, condition),
+                    DivertTarget::Variable(name) => write!(f, "{} -> {{{}}}{}", kind, name, condition),
+                    DivertTarget::External { path, args } => write!(f, "external {}({} args){}", path, args, condition),
+                }
+            }
+            Object::Glue(..) => write!(f, "glue"),
+            Object::NativeFunctionCall(call) => write!(f, "{:?}", call),
+            Object::Story(..) => write!(f, "story"),
+            Object::Tag(tag) => write!(f, "# {}", tag.text()),
+            Object::VariableAssignment(assignment) => {
+                let scope = match (assignment.is_new_declaration, assignment.is_global) {
+                    (true, true) => "VAR ",
+                    (true, false) => "temp ",
+                    (false, _) => "",
+                };
+                write!(f, "{}{} =", scope, assignment.variable_name)
+            }
+            Object::VariableReference(reference) => match &**reference {
+                VariableReference::Variable(name) => write!(f, "read {}", name),
+                VariableReference::PathForCount(path) => write!(f, "read count {}", path),
+            },
+            Object::Value(Value::String(text)) => write!(f, "{:?}", text),
+            Object::Value(Value::List(list)) => write!(f, "list ({})", Value::List(list.clone())),
+            Object::Value(Value::DivertTarget(path)) => write!(f, "-> {}", path),
+            Object::Value(Value::VariablePointer(pointer)) => write!(f, "ref {}", pointer.name),
+            Object::Value(value) => write!(f, "{}", value),
+            Object::Void => write!(f, "void"),
+        }
+    }
+}
+
 impl std::cmp::PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         use Object::*;
@@ -305,3 +373,52 @@ impl TryInto<Path> for Object {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn knot_with_stitch() -> Rc<Container> {
+        Rc::new_cyclic(|root| {
+            let knot = Rc::new_cyclic(|knot| {
+                let stitch = Rc::new(Container {
+                    name: "stitch".to_owned(),
+                    parent: Some(Pointer { container: Some(knot.clone()), index: None }),
+                    ..Container::default()
+                });
+                Container {
+                    name: "knot".to_owned(),
+                    content: vec![Object::Container(stitch)],
+                    parent: Some(Pointer { container: Some(root.clone()), index: None }),
+                    ..Container::default()
+                }
+            });
+            Container { content: vec![Object::Container(knot)], ..Container::default() }
+        })
+    }
+
+    #[test]
+    fn rc_container_name() {
+        let root = knot_with_stitch();
+        let knot = match &root.content[0] {
+            Object::Container(knot) => knot,
+            _ => unreachable!(),
+        };
+        assert_eq!(NamedObject::name(knot), "knot");
+        assert!(NamedObject::has_valid_name(knot));
+        assert!(!NamedObject::has_valid_name(&root));
+    }
+
+    #[test]
+    fn path_is_root_first() {
+        let root = knot_with_stitch();
+        let knot = root.content[0].clone();
+        let stitch = match &knot {
+            Object::Container(knot) => knot.content[0].clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(Object::Container(root.clone()).path().to_string(), "");
+        assert_eq!(knot.path().to_string(), "knot");
+        assert_eq!(stitch.path().to_string(), "knot.stitch");
+    }
+}
@@ -0,0 +1,130 @@
+use std::rc::Rc;
+use super::Choice;
+
+/// Receives the output of a `Story` as it is produced, rather than having to poll `current_text`
+/// and `current_tags` once the story stops.
+///
+/// An `OutputSink` can be installed on a `Story` using `Story::set_output_sink`. All of the methods
+/// have empty default implementations, so only the events of interest need to be handled.
+///
+/// Text is reported as it is output, with its whitespace cleaned up as in `current_text`.
+/// Whitespace at the end of the output is held back until more text follows it, as glue or the end
+/// of a function may still remove it, so the end of a line is reported when `Story::continue_story`
+/// returns it.
+pub trait OutputSink {
+    /// Called whenever a piece of text is output. Line breaks are reported separately.
+    fn on_text(&mut self, _text: &str) {}
+
+    /// Called whenever a tag is output.
+    fn on_tag(&mut self, _tag: &str) {}
+
+    /// Called whenever a line break is output.
+    fn on_line_end(&mut self) {}
+
+    /// Called when the story stops to present choices to the player, after the text of the last line.
+    fn on_choices(&mut self, _choices: &[Rc<Choice>]) {}
+}
+
+#[derive(Clone, Debug)]
+enum SinkEvent {
+    Text(String),
+    Tag(String),
+    LineEnd,
+}
+
+/// Cleans up the whitespace of the text being output, one piece at a time, in the same way as
+/// `Story::current_text`, and queues it to be sent to an `OutputSink`.
+#[derive(Clone, Debug)]
+pub(crate) struct OutputSinkWriter {
+    /// Whether the text so far is empty or ends in a newline, where inline whitespace is dropped.
+    at_start_of_line: bool,
+    /// Whether the text so far ends in inline whitespace, and whether that becomes a space if more
+    /// text follows on the same line.
+    in_whitespace: bool,
+    space_pending: bool,
+    /// The newlines at the end of the text so far, which have not been sent yet.
+    newlines_held: usize,
+    events: Vec<SinkEvent>,
+}
+
+impl Default for OutputSinkWriter {
+    fn default() -> Self {
+        OutputSinkWriter {
+            at_start_of_line: true,
+            in_whitespace: false,
+            space_pending: false,
+            newlines_held: 0,
+            events: vec![],
+        }
+    }
+}
+
+impl OutputSinkWriter {
+    pub(crate) fn write_text(&mut self, text: &str) {
+        let mut cleaned = String::new();
+        for ch in text.chars() {
+            if ch == ' ' || ch == '\t' {
+                if !self.in_whitespace {
+                    self.in_whitespace = true;
+                    self.space_pending = !self.at_start_of_line;
+                }
+                continue;
+            }
+            if ch == '\n' {
+                self.newlines_held += 1;
+                self.at_start_of_line = true;
+            } else {
+                if self.newlines_held > 0 {
+                    if !cleaned.is_empty() {
+                        self.events.push(SinkEvent::Text(std::mem::take(&mut cleaned)));
+                    }
+                    self.end_line();
+                }
+                if self.space_pending {
+                    cleaned.push(' ');
+                }
+                cleaned.push(ch);
+                self.at_start_of_line = false;
+            }
+            self.in_whitespace = false;
+            self.space_pending = false;
+        }
+        if !cleaned.is_empty() {
+            self.events.push(SinkEvent::Text(cleaned));
+        }
+    }
+
+    pub(crate) fn write_tag(&mut self, tag: &str) {
+        self.events.push(SinkEvent::Tag(tag.to_owned()));
+    }
+
+    /// Ends the line, sending the newlines that were held back.
+    pub(crate) fn end_line(&mut self) {
+        for _ in 0..self.newlines_held {
+            self.events.push(SinkEvent::LineEnd);
+        }
+        self.newlines_held = 0;
+    }
+
+    /// Forgets the whitespace that was held back, after whitespace has been removed from the end of
+    /// the output, and holds back the whitespace which remains instead. `at_start` is whether there
+    /// is no text before that whitespace.
+    pub(crate) fn rewind(&mut self, trailing_whitespace: &str, at_start: bool) {
+        self.at_start_of_line = at_start;
+        self.in_whitespace = false;
+        self.space_pending = false;
+        self.newlines_held = 0;
+        self.write_text(trailing_whitespace);
+    }
+
+    /// Sends everything that has been queued to the sink.
+    pub(crate) fn send(&mut self, sink: &mut dyn OutputSink) {
+        for event in self.events.drain(..) {
+            match event {
+                SinkEvent::Text(text) => sink.on_text(&text),
+                SinkEvent::Tag(tag) => sink.on_tag(&tag),
+                SinkEvent::LineEnd => sink.on_line_end(),
+            }
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use std::ops::Index;
 use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, Visitor};
 
-#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+/// A single component of a `Path`.
+///
+/// Serializes as a number for an index, the string `"^"` for the parent, and a string for a name.
+#[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub enum Component {
     Index(usize),
     Name(String),
@@ -18,7 +24,12 @@ impl Display for Component {
     }
 }
 
-#[derive(Clone, Hash, Eq, PartialEq, Debug, Default)]
+/// A path to some content in the story.
+///
+/// Paths are written (and serialized) as their components separated by `.`, such as
+/// `knot.stitch.0`. Relative paths start with a `.`, and `^` refers to the parent container, such as
+/// `.^.^.3`.
+#[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
 pub struct Path {
     pub(crate) parts: Vec<Component>,
     pub(crate) is_relative: bool,
@@ -33,6 +44,32 @@ impl Path {
         self.parts.is_empty()
     }
 
+    /// Whether this path is equal to, or is a path to content within, the other path.
+    pub fn starts_with(&self, prefix: &Path) -> bool {
+        self.is_relative == prefix.is_relative && self.parts.starts_with(&prefix.parts)
+    }
+
+    /// Resolves a path relative to this one, where each leading `^` of the relative path removes
+    /// one component of this path. An absolute path is returned as is.
+    pub fn appending(&self, other: &Path) -> Path {
+        if !other.is_relative {
+            return other.clone();
+        }
+        let upward_moves = other.parts
+            .iter()
+            .take_while(|part| **part == Component::Parent)
+            .count();
+        let kept = self.parts.len().saturating_sub(upward_moves);
+        Path {
+            parts: self.parts[..kept]
+                .iter()
+                .chain(&other.parts[upward_moves..])
+                .cloned()
+                .collect(),
+            is_relative: self.is_relative,
+        }
+    }
+
     pub(crate) fn without_last_component(&self) -> Path {
         let mut clone = self.clone();
         clone.parts.pop();
@@ -71,6 +108,136 @@ impl Display for Path {
             .iter()
             .map(|comp| format!("{}", comp))
             .collect();
+        if self.is_relative {
+            write!(f, ".")?;
+        }
         write!(f, "{}", part_strs.join("."))
     }
 }
+
+/// The error returned when parsing a `Path` or `Component` from a string with an empty component,
+/// such as `a..b` or `a.`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ParsePathError {
+    path: String,
+}
+
+impl Display for ParsePathError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Invalid path '{}': paths cannot have empty components", self.path)
+    }
+}
+
+impl std::error::Error for ParsePathError {}
+
+impl FromStr for Component {
+    type Err = ParsePathError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParsePathError { path: s.to_owned() });
+        }
+        if s == "^" {
+            return Ok(Component::Parent);
+        }
+        Ok(match s.parse() {
+            Ok(index) => Component::Index(index),
+            Err(..) => Component::Name(s.to_owned()),
+        })
+    }
+}
+
+impl FromStr for Path {
+    type Err = ParsePathError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_relative = s.starts_with('.');
+        let parts = if is_relative { &s[1..] } else { s };
+        let parts = if parts.is_empty() {
+            vec![]
+        } else {
+            parts.split('.')
+                .map(|part| part.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| ParsePathError { path: s.to_owned() })?
+        };
+        Ok(Path { parts, is_relative })
+    }
+}
+
+impl Serialize for Component {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Component::Index(index) => serializer.serialize_u64(*index as u64),
+            Component::Name(name) => serializer.serialize_str(name),
+            Component::Parent => serializer.serialize_str("^"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Component {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ComponentVisitor;
+
+        impl<'de> Visitor<'de> for ComponentVisitor {
+            type Value = Component;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "an index or a name")
+            }
+
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<Component, E> {
+                Ok(Component::Index(index as usize))
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Component, E> {
+                match name {
+                    "^" => Ok(Component::Parent),
+                    _ => Ok(Component::Name(name.to_owned())),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ComponentVisitor)
+    }
+}
+
+impl Serialize for Path {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Path {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_with_empty_components_are_invalid() {
+        assert!("a..b".parse::<Path>().is_err());
+        assert!("a.".parse::<Path>().is_err());
+        assert!("..".parse::<Path>().is_err());
+        assert_eq!("a..b".parse::<Path>().unwrap_err().to_string(), "Invalid path 'a..b': paths cannot have empty components");
+        assert_eq!("".parse::<Path>(), Ok(Path::default()));
+        assert_eq!(".^.c-0".parse::<Path>().map(|path| path.to_string()), Ok(".^.c-0".to_owned()));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let path: Path = ".^.^.knot.3".parse().unwrap();
+        let json = serde_json::to_string(&path).unwrap();
+        assert_eq!(json, r#"".^.^.knot.3""#);
+        assert_eq!(serde_json::from_str::<Path>(&json).unwrap(), path);
+        assert!(serde_json::from_str::<Path>(r#""a..b""#).is_err());
+
+        let components = vec![Component::Index(3), Component::Name("knot".to_owned()), Component::Parent];
+        let json = serde_json::to_string(&components).unwrap();
+        assert_eq!(json, r#"[3,"knot","^"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Component>>(&json).unwrap(), components);
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt::{self, Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use serde::{Serialize, Deserialize};
+use super::{panic_message, Limits, Path, Story, StoryError};
+
+/// Plays a story many times, making random choices, to find runs which fail.
+///
+/// Each run plays a fresh copy of the story from its current state, with its own seed, which is
+/// used both for choosing and as the story's seed, so that a failure can be reproduced by
+/// replaying its seed and choices. A run fails if it panics, exceeds the `Limits`, or reaches a
+/// dead end: no more content and no choices, without having reached an `END` or `DONE`.
+///
+/// Panics are caught and reported as failures, but the panic hook is left alone, so by default
+/// the message of each panic is also printed to stderr as it happens. Use `std::panic::set_hook`
+/// around `run` to silence them.
+#[derive(Clone, Debug)]
+pub struct Playtester {
+    pub runs: usize,
+    pub seed: u64,
+    /// Runs which have made this many choices are stopped, and considered successful.
+    pub max_choices: usize,
+    /// The limits applied to the story during each run, so runs which are stuck in a loop fail
+    /// rather than running forever.
+    pub limits: Limits,
+}
+
+impl Default for Playtester {
+    fn default() -> Self {
+        Playtester {
+            runs: 1000,
+            seed: 0,
+            max_choices: 1000,
+            limits: Limits {
+                max_steps_per_continue: Some(1_000_000),
+                max_call_stack_depth: Some(10_000),
+                ..Limits::default()
+            },
+        }
+    }
+}
+
+/// How a playtest run failed.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FailureKind {
+    /// The story panicked, with this message.
+    Panic(String),
+    /// The story ran out of content without offering choices or reaching `END` or `DONE`, with
+    /// the path to the last content that was executed and a description of what may be missing.
+    DeadEnd { path: Option<Path>, message: String },
+    /// The story exceeded one of the playtester's `Limits`, with the error's description.
+    LimitExceeded(String),
+}
+
+/// A failed playtest run, with everything needed to replay it.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PlaytestFailure {
+    pub seed: u64,
+    /// The index of the choice made at each turn, in order.
+    pub choices: Vec<usize>,
+    pub kind: FailureKind,
+}
+
+/// The results of a `Playtester`.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct PlaytestReport {
+    pub runs: usize,
+    pub failures: Vec<PlaytestFailure>,
+}
+
+impl Display for PlaytestReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "{} of {} runs failed", self.failures.len(), self.runs)?;
+        for failure in &self.failures {
+            let choices = failure.choices.iter().map(ToString::to_string).collect::<Vec<_>>().join(",");
+            match &failure.kind {
+                FailureKind::Panic(message) => writeln!(f, "seed {}: panicked: {}", failure.seed, message)?,
+                FailureKind::DeadEnd { path: Some(path), message } => writeln!(f, "seed {}: dead end at {}: {}", failure.seed, path, message)?,
+                FailureKind::DeadEnd { path: None, message } => writeln!(f, "seed {}: dead end: {}", failure.seed, message)?,
+                FailureKind::LimitExceeded(message) => writeln!(f, "seed {}: {}", failure.seed, message)?,
+            }
+            writeln!(f, "  choices: [{}]", choices)?;
+        }
+        Ok(())
+    }
+}
+
+impl Playtester {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plays the story `runs` times.
+    pub fn run(&self, story: &Story) -> PlaytestReport {
+        let failures = (0..self.runs)
+            .filter_map(|run| self.play(story, self.seed.wrapping_add(run as u64)))
+            .collect();
+        PlaytestReport { runs: self.runs, failures }
+    }
+
+    fn play(&self, story: &Story, seed: u64) -> Option<PlaytestFailure> {
+        let mut story = story.clone();
+        story.set_seed(seed);
+        story.set_limits(self.limits);
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let mut choices = vec![];
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            loop {
+                while story.can_continue() {
+                    match story.try_continue_story() {
+                        Ok(_) => {}
+                        Err(StoryError::LimitExceeded(error)) => return Some(FailureKind::LimitExceeded(error.to_string())),
+                        Err(StoryError::OutOfContent(message)) => {
+                            return Some(FailureKind::DeadEnd { path: story.current_thread().previous_path(), message });
+                        }
+                    }
+                }
+                let count = story.current_choices().len();
+                if count == 0 {
+                    return None;
+                }
+                if choices.len() >= self.max_choices {
+                    return None;
+                }
+                let index = rng.gen_range(0, count);
+                choices.push(index);
+                story.choose_choice_index(index);
+            }
+        }));
+
+        let kind = match result {
+            Ok(kind) => kind?,
+            Err(payload) => FailureKind::Panic(panic_message(&*payload)),
+        };
+        Some(PlaytestFailure { seed, choices, kind })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playtest(root: &str) -> PlaytestReport {
+        let story = Story::from_json(&format!(r#"{{"inkVersion":19,"root":{}}}"#, root)).unwrap();
+        Playtester { runs: 10, ..Playtester::new() }.run(&story)
+    }
+
+    #[test]
+    fn finds_dead_ends_after_choices() {
+        let report = playtest(r##"[["ev","str","^A","/str","/ev",{"*":".^.c-0","flg":20},"ev","str","^B","/str","/ev",{"*":".^.c-1","flg":20},{"c-0":["^Fine","\n","end",{"#f":1}],"c-1":["^Stuck","\n",{"#f":1}]}],"done",null]"##);
+        assert_eq!(report.runs, 10);
+        assert!(!report.failures.is_empty());
+        for failure in &report.failures {
+            assert_eq!(failure.choices, vec![1]);
+            assert_eq!(failure.kind, FailureKind::DeadEnd {
+                path: Some("0.c-1.1".parse().unwrap()),
+                message: "ran out of content. Do you need a '-> DONE' or '-> END'?".to_owned(),
+            });
+        }
+    }
+
+    #[test]
+    fn runs_stuck_in_a_loop_exceed_the_limits() {
+        let story = Story::from_json(r#"{"inkVersion":19,"root":[[{"->":"loop"},null],"done",{"loop":["^Loop",{"->":"loop"},null]}]}"#).unwrap();
+        let limits = Limits { max_steps_per_continue: Some(100), ..Limits::default() };
+        let report = Playtester { runs: 10, limits, ..Playtester::new() }.run(&story);
+        assert_eq!(report.failures.len(), 10);
+        for failure in &report.failures {
+            assert!(matches!(&failure.kind, FailureKind::LimitExceeded(message) if message.starts_with("Exceeded")));
+        }
+    }
+
+    #[test]
+    fn stories_which_end_pass() {
+        let report = playtest(r#"[["^Hello","\n","end",null],"done",null]"#);
+        assert!(report.failures.is_empty());
+    }
+}
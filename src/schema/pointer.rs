@@ -1,6 +1,6 @@
 use std::rc::{Weak, Rc};
 
-use super::{Container, Object, TryAsRef};
+use super::{Container, Object, Path};
 
 #[derive(Clone, Debug)]
 pub struct Pointer {
@@ -26,15 +26,6 @@ impl Pointer {
         Self::new(container, 0)
     }
 
-    pub(crate) fn to(object: &Object) -> Self {
-        let container: Rc<Container> = object.parent().unwrap().try_as_ref().cloned().unwrap();
-        let index = container.index_of(object);
-        Self { 
-            container: Some(Rc::downgrade(&container)), 
-            index,
-        }
-    }
-
     pub(crate) fn container(&self) -> Option<Rc<Container>> {
         self.container
             .as_ref()
@@ -46,10 +37,21 @@ impl Pointer {
         let container = self.container.as_ref()?.upgrade()?;
         match self.index {
             None => Some(Object::Container(container.clone())),
+            // an empty container is its own content, so that it can still be stepped into
+            Some(_) if container.content.is_empty() => Some(Object::Container(container.clone())),
             Some(index) => container.content.get(index).cloned(),
         }
     }
 
+    /// The path to the content this pointer points to, or `None` if the pointer is null.
+    pub(crate) fn path(&self) -> Option<Path> {
+        let mut path = Object::Container(self.container()?).path();
+        if let Some(index) = self.index {
+            path.join(index);
+        }
+        Some(path)
+    }
+
     pub(crate) fn is_null(&self) -> bool {
         self.container.is_none() && self.index.is_none()
     }
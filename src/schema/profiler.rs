@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+use super::Path;
+
+/// The cost of executing some part of a story.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct ProfileEntry {
+    /// The number of objects executed.
+    pub steps: usize,
+    /// The number of native function calls (arithmetic, comparisons, list operations, etc.).
+    pub native_calls: usize,
+    pub time: Duration,
+}
+
+impl ProfileEntry {
+    fn add(&mut self, other: &ProfileEntry) {
+        self.steps += other.steps;
+        self.native_calls += other.native_calls;
+        self.time += other.time;
+    }
+}
+
+/// A node of the call tree collected by a `Profiler`.
+#[derive(Clone, Default, Debug)]
+pub struct CallTreeNode {
+    /// The cost of the node itself, excluding the knots and functions it called.
+    pub own: ProfileEntry,
+    /// The knots and functions called from this node.
+    pub children: BTreeMap<Path, CallTreeNode>,
+}
+
+impl CallTreeNode {
+    /// The cost of this node, including everything it called.
+    pub fn total(&self) -> ProfileEntry {
+        let mut total = self.own;
+        for child in self.children.values() {
+            total.add(&child.total());
+        }
+        total
+    }
+}
+
+/// Attributes the cost of running a story to the knot or function that was executing, once
+/// enabled using `Story::enable_profiler`.
+///
+/// The cost is recorded for each knot (by its path) as a flat profile, and also in a call tree
+/// following the frames of the call stack, so the cost of a function can be seen separately for
+/// each knot it is called from.
+#[derive(Clone, Default, Debug)]
+pub struct Profiler {
+    flat: BTreeMap<Path, ProfileEntry>,
+    call_tree: CallTreeNode,
+    current_stack: Vec<Path>,
+    current_step: ProfileEntry,
+}
+
+fn describe(path: &Path) -> String {
+    if path.is_empty() { "(root)".to_owned() } else { path.to_string() }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts recording a step, executed with the given knots on the call stack (outermost first).
+    pub(crate) fn begin_step(&mut self, mut stack: Vec<Path>) {
+        stack.dedup();
+        self.current_stack = stack;
+        self.current_step = ProfileEntry { steps: 1, ..ProfileEntry::default() };
+    }
+
+    pub(crate) fn record_native_call(&mut self) {
+        self.current_step.native_calls += 1;
+    }
+
+    pub(crate) fn end_step(&mut self, time: Duration) {
+        self.current_step.time = time;
+        let step = self.current_step;
+        let knot = self.current_stack.last().cloned().unwrap_or_default();
+        self.flat.entry(knot).or_default().add(&step);
+        let mut node = &mut self.call_tree;
+        for path in &self.current_stack {
+            node = node.children.entry(path.clone()).or_default();
+        }
+        node.own.add(&step);
+    }
+
+    /// The cost of each knot, excluding the knots and functions it called.
+    pub fn flat(&self) -> &BTreeMap<Path, ProfileEntry> {
+        &self.flat
+    }
+
+    /// The root of the call tree. Its children are the knots at the bottom of the call stack.
+    pub fn call_tree(&self) -> &CallTreeNode {
+        &self.call_tree
+    }
+
+    /// A table of the cost of each knot, most expensive first.
+    pub fn flat_report(&self) -> String {
+        let mut entries: Vec<_> = self.flat.iter().collect();
+        entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.time));
+        let mut report = format!("{:>12} {:>10} {:>10}  knot\n", "time (us)", "steps", "calls");
+        for (path, entry) in entries {
+            let _ = writeln!(report, "{:>12} {:>10} {:>10}  {}", entry.time.as_micros(), entry.steps, entry.native_calls, describe(path));
+        }
+        report
+    }
+
+    /// The call tree, indented, with the total and own cost of each node.
+    pub fn call_tree_report(&self) -> String {
+        let mut report = format!("{:>12} {:>12} {:>10} {:>10}  knot\n", "total (us)", "own (us)", "steps", "calls");
+        Self::write_call_tree(&mut report, &self.call_tree, 0);
+        report
+    }
+
+    fn write_call_tree(report: &mut String, node: &CallTreeNode, depth: usize) {
+        let mut children: Vec<_> = node.children.iter().collect();
+        children.sort_by_key(|(_, child)| std::cmp::Reverse(child.total().time));
+        for (path, child) in children {
+            let total = child.total();
+            let _ = writeln!(
+                report,
+                "{:>12} {:>12} {:>10} {:>10}  {}{}",
+                total.time.as_micros(),
+                child.own.time.as_micros(),
+                total.steps,
+                total.native_calls,
+                "  ".repeat(depth),
+                describe(path),
+            );
+            Self::write_call_tree(report, child, depth + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Story;
+
+    #[test]
+    fn steps_are_attributed_to_the_knot_being_executed() {
+        let json = r#"{"inkVersion":19,"root":[[{"->":"start"},null],"done",{"start":["ev",{"f()":"one"},"out","/ev","\n","end",null],"one":["ev",1,"/ev","~ret",null]}]}"#;
+        let mut story = Story::from_json(json).unwrap();
+        story.enable_profiler(Profiler::new());
+        story.continue_story();
+
+        let profiler = story.take_profiler().unwrap();
+        let knots = profiler.flat().keys().map(describe).collect::<Vec<_>>();
+        assert_eq!(knots, vec!["(root)", "one", "start"]);
+        let start = &profiler.call_tree().children[&"start".parse().unwrap()];
+        let called = start.children.keys().map(describe).collect::<Vec<_>>();
+        assert_eq!(called, vec!["one"]);
+        assert_eq!(start.children[&"one".parse().unwrap()].own.steps, 4);
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use serde::{Serialize, Deserialize};
+use super::{panic_message, Story, Value};
+
+/// A request sent to a story by another process, as one line of JSON, such as
+/// `{"command":"choose","index":0}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    Load { path: String },
+    Continue,
+    Choose { index: usize },
+    GetVariable { name: String },
+    SetVariable { name: String, value: Value },
+    SaveState,
+    LoadState { state: serde_json::Value },
+    EvaluateFunction { name: String, args: Vec<Value> },
+}
+
+/// The reply to a `Request`, sent as one line of JSON. `Value`s are encoded structurally, e.g.
+/// `{"Int":3}` or `{"String":"text"}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    /// The line produced by `continue`, with its tags, and the choices if the story has stopped
+    /// to wait for one.
+    Text { text: String, tags: Vec<String>, choices: Vec<String> },
+    Variable { value: Option<Value> },
+    /// The state of the story, as saved by `Story::save_state`.
+    State { state: serde_json::Value },
+    /// The value returned by a function (if any), and the text it output.
+    Result { value: Option<Value>, text: String },
+    Error { message: String },
+}
+
+/// Performs a single request against the story. A `load` request replaces the story.
+pub fn respond(story: &mut Story, request: Request) -> Response {
+    match request {
+        Request::Load { path } => {
+            let loaded = fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|json| Story::from_json(&json).map_err(|error| error.to_string()));
+            match loaded {
+                Ok(loaded) => {
+                    *story = loaded;
+                    Response::Ok
+                }
+                Err(error) => Response::Error { message: format!("Could not load {}: {}", path, error) },
+            }
+        }
+        Request::Continue => {
+            if !story.can_continue() {
+                return Response::Error { message: "Can't continue - the story is waiting for a choice or has ended".to_owned() };
+            }
+            match story.try_continue_story() {
+                Ok(text) => Response::Text {
+                    text,
+                    tags: story.current_tags(),
+                    choices: story.current_choices()
+                        .iter()
+                        .map(|choice| choice.text().to_owned())
+                        .collect(),
+                },
+                Err(error) => Response::Error { message: error.to_string() },
+            }
+        }
+        Request::Choose { index } => {
+            let count = story.current_choices().len();
+            if index >= count {
+                return Response::Error { message: format!("Choice out of range: {} (there are {} choices)", index, count) };
+            }
+            story.choose_choice_index(index);
+            Response::Ok
+        }
+        Request::GetVariable { name } => Response::Variable { value: story.variable(&name) },
+        Request::SetVariable { name, value } => {
+            if !story.has_variable(&name) {
+                return Response::Error { message: format!("Cannot assign to a variable ({}) that hasn't been declared in the story", name) };
+            }
+            story.set_variable(&name, value);
+            Response::Ok
+        }
+        Request::SaveState => match serde_json::from_str(&story.save_state()) {
+            Ok(state) => Response::State { state },
+            Err(error) => Response::Error { message: format!("Could not save the state: {}", error) },
+        },
+        Request::LoadState { state } => match story.load_state(&state.to_string()) {
+            Ok(()) => Response::Ok,
+            Err(error) => Response::Error { message: format!("Could not load the state: {}", error) },
+        },
+        Request::EvaluateFunction { name, args } => {
+            if !story.has_path(&name) {
+                return Response::Error { message: format!("Function doesn't exist: '{}'", name) };
+            }
+            let (value, text) = story.evaluate_function(&name, &args);
+            Response::Result { value, text }
+        }
+    }
+}
+
+/// Reads newline-delimited JSON requests from `input` until it ends, writing a response line to
+/// `output` for each. Lines which are not valid requests are answered with an error.
+///
+/// A request which panics (such as evaluating a function which exceeds the story's `Limits`) is
+/// also answered with an error, and the story is returned to its state before that request, so
+/// the server keeps answering. The panic hook is left alone, so the panic is still printed to
+/// stderr by default.
+pub fn serve<R, W>(story: &mut Story, input: R, mut output: W) -> io::Result<()> where R: BufRead, W: Write {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue }
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => {
+                let previous = story.clone();
+                match panic::catch_unwind(AssertUnwindSafe(|| respond(story, request))) {
+                    Ok(response) => response,
+                    Err(payload) => {
+                        *story = previous;
+                        Response::Error { message: panic_message(&*payload) }
+                    }
+                }
+            }
+            Err(error) => Response::Error { message: format!("Invalid request: {}", error) },
+        };
+        serde_json::to_writer(&mut output, &response)?;
+        writeln!(output)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Limits;
+
+    #[test]
+    fn serve_answers_each_request() {
+        let mut story = Story::from_json(r##"{"inkVersion":19,"root":[["ev",{"VAR?":"x"},"out","/ev","\n","ev","str","^Go","/str","/ev",{"*":".^.c-0","flg":20},{"c-0":["^Gone","\n","end",{"#f":1}]}],"done",{"double":[{"temp=":"n"},"ev",{"VAR?":"n"},2,"*","/ev","~ret",null],"global decl":["ev",1,{"VAR=":"x"},"/ev","end",null]}]}"##).unwrap();
+        let requests = [
+            r#"{"command":"set_variable","name":"x","value":{"Int":5}}"#,
+            r#"{"command":"continue"}"#,
+            r#"{"command":"evaluate_function","name":"double","args":[{"Int":4}]}"#,
+            r#"{"command":"choose","index":3}"#,
+            r#"{"command":"continue"}"#,
+            r#"not json"#,
+        ];
+        let mut output = vec![];
+        serve(&mut story, requests.join("\n").as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().collect::<Vec<_>>(), vec![
+            r#"{"status":"ok"}"#,
+            r#"{"status":"text","text":"5\n","tags":[],"choices":["Go"]}"#,
+            r#"{"status":"result","value":{"Int":8},"text":""}"#,
+            r#"{"status":"error","message":"Choice out of range: 3 (there are 1 choices)"}"#,
+            r#"{"status":"error","message":"Can't continue - the story is waiting for a choice or has ended"}"#,
+            r#"{"status":"error","message":"Invalid request: expected ident at line 1 column 2"}"#,
+        ]);
+    }
+
+    #[test]
+    fn serve_answers_requests_after_a_runaway_story() {
+        let mut story = Story::from_json(r#"{"inkVersion":19,"root":[[{"->":"loop"},null],"done",{"loop":["^Loop",{"->":"loop"},null],"spin":["^Spin",{"->":"spin"},null],"global decl":["ev",1,{"VAR=":"x"},"/ev","end",null]}]}"#).unwrap();
+        story.set_limits(Limits { max_steps_per_continue: Some(10), ..Limits::default() });
+        let requests = [
+            r#"{"command":"continue"}"#,
+            r#"{"command":"evaluate_function","name":"spin","args":[]}"#,
+            r#"{"command":"get_variable","name":"x"}"#,
+        ];
+        let mut output = vec![];
+        serve(&mut story, requests.join("\n").as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let responses = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect::<Vec<Response>>();
+        assert_eq!(responses.len(), 3);
+        for response in &responses[..2] {
+            match response {
+                Response::Error { message } => assert!(message.starts_with("Exceeded 10 steps"), "{}", message),
+                other => panic!("expected an error, found {:?}", other),
+            }
+        }
+        assert!(matches!(&responses[2], Response::Variable { value: Some(Value::Int(1)) }));
+    }
+
+    #[test]
+    fn saved_states_can_be_loaded() {
+        let mut story = Story::from_json(r#"{"inkVersion":19,"root":[["^One","\n","^Two","\n","end",null],"done",null]}"#).unwrap();
+        respond(&mut story, Request::Continue);
+        let state = match respond(&mut story, Request::SaveState) {
+            Response::State { state } => state,
+            other => panic!("expected a state, found {:?}", other),
+        };
+        respond(&mut story, Request::Continue);
+        assert!(!story.can_continue());
+
+        assert!(matches!(respond(&mut story, Request::LoadState { state }), Response::Ok));
+        match respond(&mut story, Request::Continue) {
+            Response::Text { text, .. } => assert_eq!(text, "Two\n"),
+            other => panic!("expected text, found {:?}", other),
+        }
+    }
+}
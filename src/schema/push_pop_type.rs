@@ -1,6 +1,7 @@
 use std::fmt::{self, Display, Formatter};
+use serde::{Serialize, Deserialize};
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum PushPopType {
     Tunnel,
     Function,
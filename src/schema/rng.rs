@@ -0,0 +1,122 @@
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+/// A source of random numbers for a `Story`, used by `RANDOM`, `LIST_RANDOM` and shuffles.
+///
+/// The story reseeds the generator (based on the story seed and previous results) every time it
+/// needs random numbers, so a generator only has to produce the same sequence for the same seed.
+pub trait InkRng {
+    /// Restarts the generator from the given seed.
+    fn reseed(&mut self, seed: i64);
+
+    /// Generates the next random number, which must not be negative.
+    fn next(&mut self) -> i64;
+}
+
+/// The default `InkRng`, backed by a `Pcg64` generator.
+#[derive(Clone, Debug)]
+pub struct PcgRng {
+    rng: Pcg64,
+}
+
+impl Default for PcgRng {
+    fn default() -> Self {
+        Self { rng: Pcg64::seed_from_u64(0) }
+    }
+}
+
+impl InkRng for PcgRng {
+    fn reseed(&mut self, seed: i64) {
+        self.rng = Pcg64::seed_from_u64(seed as u64);
+    }
+
+    fn next(&mut self) -> i64 {
+        self.rng.gen_range(0, i64::MAX)
+    }
+}
+
+/// An `InkRng` implementing the algorithm of .NET's `System.Random` (as constructed with a seed), so
+/// that shuffles and random numbers match those of the official runtime exactly. Seeds are
+/// truncated to 32 bits, as they are in C#.
+#[derive(Clone, Debug)]
+pub struct DotNetRandom {
+    seed_array: [i32; 56],
+    inext: usize,
+    inextp: usize,
+}
+
+impl DotNetRandom {
+    const MBIG: i32 = i32::MAX;
+    const MSEED: i32 = 161_803_398;
+
+    pub fn new(seed: i32) -> Self {
+        let mut seed_array = [0; 56];
+        let subtraction = if seed == i32::MIN { i32::MAX } else { seed.abs() };
+        let mut mj = Self::MSEED.wrapping_sub(subtraction);
+        seed_array[55] = mj;
+        let mut mk = 1i32;
+        for i in 1..55 {
+            let ii = (21 * i) % 55;
+            seed_array[ii] = mk;
+            mk = mj.wrapping_sub(mk);
+            if mk < 0 {
+                mk = mk.wrapping_add(Self::MBIG);
+            }
+            mj = seed_array[ii];
+        }
+        for _ in 1..5 {
+            for i in 1..56 {
+                seed_array[i] = seed_array[i].wrapping_sub(seed_array[1 + (i + 30) % 55]);
+                if seed_array[i] < 0 {
+                    seed_array[i] = seed_array[i].wrapping_add(Self::MBIG);
+                }
+            }
+        }
+        Self { seed_array, inext: 0, inextp: 21 }
+    }
+
+    /// Equivalent to `Random.Next()`: a number in the range `[0, int.MaxValue)`.
+    pub fn next_int(&mut self) -> i32 {
+        self.inext += 1;
+        if self.inext >= 56 { self.inext = 1; }
+        self.inextp += 1;
+        if self.inextp >= 56 { self.inextp = 1; }
+
+        let mut result = self.seed_array[self.inext].wrapping_sub(self.seed_array[self.inextp]);
+        if result == Self::MBIG {
+            result -= 1;
+        }
+        if result < 0 {
+            result = result.wrapping_add(Self::MBIG);
+        }
+        self.seed_array[self.inext] = result;
+        result
+    }
+}
+
+impl Default for DotNetRandom {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl InkRng for DotNetRandom {
+    fn reseed(&mut self, seed: i64) {
+        *self = Self::new(seed as i32);
+    }
+
+    fn next(&mut self) -> i64 {
+        self.next_int() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_net_random_matches_system_random() {
+        assert_eq!(DotNetRandom::new(0).next_int(), 1559595546);
+        assert_eq!(DotNetRandom::new(42).next_int(), 1434747710);
+    }
+}
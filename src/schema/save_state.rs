@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use super::{Path, PushPopType, Value};
+
+/// The state of a story in progress, as saved by `Story::save_state`. Content is referred to by
+/// path, so a state can only be loaded into the story it was saved from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SaveState {
+    pub(crate) version: u32,
+    pub(crate) threads: Vec<ThreadState>,
+    pub(crate) thread_counter: usize,
+    pub(crate) global_variables: BTreeMap<String, Value>,
+    /// The values on the evaluation stack, where `None` is a void result.
+    pub(crate) evaluation_stack: Vec<Option<Value>>,
+    pub(crate) output_stream: Vec<OutputState>,
+    pub(crate) current_choices: Vec<ChoiceState>,
+    pub(crate) current_turn_index: usize,
+    pub(crate) visit_counts: BTreeMap<Path, usize>,
+    pub(crate) turn_indices: BTreeMap<Path, usize>,
+    pub(crate) story_seed: u64,
+    pub(crate) previous_random: u64,
+}
+
+impl SaveState {
+    /// The version of the save format, which is increased whenever it changes incompatibly.
+    pub(crate) const VERSION: u32 = 1;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct ThreadState {
+    pub(crate) index: usize,
+    pub(crate) elements: Vec<ElementState>,
+    pub(crate) previous_path: Option<Path>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct ElementState {
+    pub(crate) path: Option<Path>,
+    pub(crate) in_expression_evaluation: bool,
+    pub(crate) temporary_variables: BTreeMap<String, Value>,
+    pub(crate) push_pop_type: PushPopType,
+    pub(crate) evaluation_stack_size_when_called: usize,
+    pub(crate) function_start_in_output_stream: Option<usize>,
+}
+
+/// An object in the output stream.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) enum OutputState {
+    Value(Value),
+    Glue,
+    Tag(String),
+    BeginString,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct ChoiceState {
+    pub(crate) text: String,
+    pub(crate) target_path: Path,
+    pub(crate) is_invisible_default: bool,
+    pub(crate) thread: ThreadState,
+}
@@ -1,13 +1,15 @@
 use std::convert::TryInto;
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::fmt::{self, Debug, Formatter};
-use rand_pcg::Pcg64;
-use rand::{Rng, SeedableRng};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use serde::de::Error as _;
 
 use super::*;
 
+/// A frame of the call stack. Each knot, tunnel, or function that has been entered and not
+/// yet returned from has its own `Element`.
 #[derive(Clone, Debug)]
 pub struct Element {
     current_pointer: Pointer,
@@ -17,7 +19,9 @@ pub struct Element {
     push_pop_type: PushPopType,
 
     evaluation_stack_size_when_called: usize,
-    function_start_in_output_stream: usize,
+    /// Where the output of this function call starts in the output stream, until it has output
+    /// some non-whitespace text.
+    function_start_in_output_stream: Option<usize>,
 }
 
 impl Element {
@@ -28,11 +32,32 @@ impl Element {
             temporary_variables: HashMap::new(),
             push_pop_type,
             evaluation_stack_size_when_called: 0,
-            function_start_in_output_stream: 0,
+            function_start_in_output_stream: None,
         }
     }
+
+    /// How this frame was entered, which determines how it may be left.
+    pub fn push_pop_type(&self) -> PushPopType {
+        self.push_pop_type
+    }
+
+    /// The path to the content this frame is currently pointing at, or `None` if it points at
+    /// nothing.
+    pub fn current_path(&self) -> Option<Path> {
+        self.current_pointer.path()
+    }
+
+    pub fn temporary_variables(&self) -> &HashMap<String, Object> {
+        &self.temporary_variables
+    }
+
+    pub fn in_expression_evaluation(&self) -> bool {
+        self.in_expression_evaluation
+    }
 }
 
+/// A thread of execution, with its own call stack. Threads are forked to collect the choices
+/// of a `<- thread`, so there may be several at once.
 #[derive(Clone, Debug, Default)]
 pub struct Thread {
     elements: Vec<Element>,
@@ -49,10 +74,25 @@ impl Thread {
         }
     }
 
-    fn current_element(&self) -> &Element {
+    /// A number identifying this thread, unique within the story.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The frames of this thread's call stack, from the outermost frame to the current one.
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    pub fn current_element(&self) -> &Element {
         self.elements.last().unwrap()
     }
 
+    /// The path to the content this thread executed most recently, if any.
+    pub fn previous_path(&self) -> Option<Path> {
+        self.previous_pointer.path()
+    }
+
     fn can_pop(&self, push_pop_type: Option<PushPopType>) -> bool {
         if self.elements.len() <= 1 { return false }
         match push_pop_type {
@@ -76,10 +116,9 @@ impl Thread {
 /// in Rust.
 ///
 /// Also note that all stuff related to patching (`StatePatch`) and asynchronous *anything* has been
-/// removed, as they are not relevant additions in a Rust implementation. The saving will not be
-/// handled internally (`Story` will implement `Serialize`/`Deserialize`), so a simple `story.clone()` will
-/// be enough to take a snapshot and save it in the background on another thread while the game
-/// still plays. Asynchronous features are just out of scope for this project.
+/// removed, as they are not relevant additions in a Rust implementation. A simple `story.clone()`
+/// is enough to take a snapshot, and `save_state` produces JSON which can be written out while the
+/// game still plays. Asynchronous features are just out of scope for this project.
 #[derive(Clone)]
 pub struct Story {
     // Story stuff
@@ -94,15 +133,36 @@ pub struct Story {
     //variable_observers: HashMap<String, Vec<Rc<RefCell<dyn FnMut(&String, &Value)>>>>,
 
     has_validated_externals: bool,
+    /// The installed formatter, or `None` to use a `CanonicalValueFormatter` following the
+    /// story's numeric mode.
+    value_formatter: Option<Rc<dyn ValueFormatter>>,
+    numeric_mode: NumericMode,
+    string_table: Option<Rc<StringTable>>,
+    output_sink: Option<Rc<RefCell<dyn OutputSink>>>,
+    /// The output of the current line which is yet to be sent to the output sink.
+    output_sink_writer: OutputSinkWriter,
+    tracer: Option<Rc<RefCell<dyn Tracer>>>,
+    /// The objects executed while looking ahead past the end of a line, which are only traced once
+    /// they're certain not to be rewound.
+    pending_traces: Vec<PendingTrace>,
+    coverage: Option<Coverage>,
+    profiler: Option<Profiler>,
+    limits: Limits,
+    steps_this_continue: usize,
+    /// The state of the story when the output last ended in a newline, which is returned to if the
+    /// story turns out to have continued past the end of the line.
+    state_snapshot: Option<Box<Story>>,
 
     // StoryState stuff
     output_stream: Vec<Object>,
-    current_text: RefCell<Option<String>>,
-    current_tags: RefCell<Option<Vec<String>>>,
+    /// The number of strings being built in the output stream, between `BeginString` and
+    /// `EndString`.
+    string_evaluation_depth: usize,
     current_choices: Vec<Rc<Choice>>,
 
     diverted_pointer: Option<Pointer>,
 
+    rng: Rc<RefCell<dyn InkRng>>,
     story_seed: u64,
     previous_random: u64,
     did_safe_exit: bool,
@@ -135,11 +195,82 @@ impl Story {
 
     /// The minimum legacy version of ink that can be loaded by the current version of the code.
     pub const INK_VERSION_MINIMUM_COMPATIBLE: u32 = 18;
+
+    /// Creates a story at the start of the given content, using the default `ValueFormatter`
+    /// (`CanonicalValueFormatter`) and `InkRng` (`PcgRng`).
+    pub(crate) fn new(main_container: Rc<Container>, list_definitions: ListDefinitions) -> Self {
+        let start_of_root = Pointer::to_start_of_container(&main_container);
+        Story {
+            temporary_evaluation_container: None,
+
+            main_container,
+            list_definitions,
+
+            has_validated_externals: false,
+            value_formatter: None,
+            numeric_mode: NumericMode::default(),
+            string_table: None,
+            output_sink: None,
+            output_sink_writer: OutputSinkWriter::default(),
+            tracer: None,
+            pending_traces: vec![],
+            coverage: None,
+            profiler: None,
+            limits: Limits::default(),
+            steps_this_continue: 0,
+            state_snapshot: None,
+
+            output_stream: vec![],
+            string_evaluation_depth: 0,
+            current_choices: vec![],
+
+            diverted_pointer: None,
+
+            rng: Rc::new(RefCell::new(PcgRng::default())),
+            story_seed: 0,
+            previous_random: 0,
+            did_safe_exit: false,
+
+            current_turn_index: 0,
+            visit_counts: HashMap::new(),
+            turn_indices: HashMap::new(),
+
+            global_variables: HashMap::new(),
+            default_global_variables: HashMap::new(),
+            evaluation_stack: vec![],
+
+            threads: vec![Thread::new(PushPopType::Tunnel, start_of_root.clone())],
+            thread_counter: 0,
+            start_of_root,
+        }
+    }
+
+    /// Loads a story from the JSON produced by the ink compiler (inklecate), ready to be played
+    /// from the start.
+    ///
+    /// Like the official runtime, the story's seed is initially based on the time at which it was
+    /// loaded. Use `set_seed` for reproducible random numbers.
+    pub fn from_json(json: &str) -> Result<Story, serde_json::Error> {
+        let content = ink_json::load(json)?;
+        let mut story = Story::new(content.main_container, content.list_definitions);
+        let time_seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.subsec_millis())
+            .unwrap_or(0);
+        story.set_seed(time_seed as u64 % 100);
+        story.reset_globals();
+        Ok(story)
+    }
 }
 
 // Accessors
 impl Story {
+    /// The choices available to the player. This is empty while the story can continue, as choices
+    /// always come at the end of the content.
     pub fn current_choices(&self) -> Vec<Rc<Choice>> {
+        if self.can_continue() {
+            return vec![];
+        }
         // current choices does not include the invisible default choice
         self.current_choices
             .iter()
@@ -148,57 +279,448 @@ impl Story {
             .collect()
     }
 
-    pub fn current_text(&mut self) -> String {
-        if let Some(ref text) = *self.current_text.borrow() {
-            return text.clone();
-        }
-
+    /// The text produced by the last `continue_story`.
+    pub fn current_text(&self) -> String {
         let text = self.output_stream
             .iter()
             .filter_map(TryAsRef::<String>::try_as_ref)
             .map(|string| string.as_str())
             .collect::<String>();
-        *self.current_text.borrow_mut() = Some(text.clone());
-        text
+        clean_output_whitespace(&text)
     }
 
     pub fn current_tags(&self) -> Vec<String> {
-        if let Some(ref tags) = *self.current_tags.borrow() {
-            return tags.clone();
-        }
-
-        let tags = self.output_stream
+        self.output_stream
             .iter()
             .filter_map(TryAsRef::<Rc<Tag>>::try_as_ref)
             .map(|tag| tag.text().to_owned())
-            .collect::<Vec<_>>();
-        *self.current_tags.borrow_mut() = Some(tags.clone());
-        tags
+            .collect()
+    }
+
+    /// The definitions of all the lists declared in this story.
+    pub fn list_definitions(&self) -> &ListDefinitions {
+        &self.list_definitions
+    }
+
+    /// The current tags, parsed into structured `TagValue`s by the given `TagParser`.
+    pub fn current_tag_values(&self, parser: &TagParser) -> Vec<TagValue> {
+        parser.parse_all(&self.current_tags())
     }
 
     pub fn can_continue(&self) -> bool {
         !self.current_pointer().is_null()
     }
 
-    fn output_stream_dirty(&self) {
-        self.current_text.borrow_mut().take();
-        self.current_tags.borrow_mut().take();
+    /// Installs the `ValueFormatter` that is used to convert values to text when they are printed
+    /// by the story. By default, a `CanonicalValueFormatter` with the story's numeric mode is used.
+    pub fn set_value_formatter<F>(&mut self, formatter: F) where F: ValueFormatter + 'static {
+        self.value_formatter = Some(Rc::new(formatter));
+    }
+
+    /// Removes the installed `ValueFormatter`, going back to the default.
+    pub fn clear_value_formatter(&mut self) {
+        self.value_formatter = None;
+    }
+
+    pub fn numeric_mode(&self) -> NumericMode {
+        self.numeric_mode
+    }
+
+    /// Sets how numbers behave when evaluated by this story. Use `NumericMode::CSharp` to get the
+    /// same results as the official runtime.
+    ///
+    /// Unless a custom `ValueFormatter` is installed, numbers are also printed the way they are in
+    /// this mode.
+    pub fn set_numeric_mode(&mut self, numeric_mode: NumericMode) {
+        self.numeric_mode = numeric_mode;
+    }
+
+    /// The seed of the story's random number generation.
+    pub fn seed(&self) -> u64 {
+        self.story_seed
+    }
+
+    /// Sets the seed of the story's random number generation, like calling `SEED_RANDOM` from ink.
+    /// This may be done at any time. The seed and the position in its sequence of random numbers
+    /// are included in `save_state`, so a loaded story generates the same numbers it would have.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.story_seed = seed;
+        self.previous_random = 0;
+    }
+
+    /// Sets the random number generator used by `RANDOM`, `LIST_RANDOM` and shuffles. Use
+    /// `DotNetRandom` to get the same results as the official runtime.
+    ///
+    /// The generator is reseeded before every use, so it is shared with clones of this story.
+    pub fn set_rng<R>(&mut self, rng: R) where R: InkRng + 'static {
+        self.rng = Rc::new(RefCell::new(rng));
+    }
+
+    /// Extracts all the literal text from this story, so that it can be translated.
+    pub fn extract_string_table(&self) -> StringTable {
+        StringTable::extract(&self.main_container)
+    }
+
+    /// Loads a (translated) `StringTable`. While a string table is loaded, any text output by the
+    /// story which has an entry in the table is replaced by the text from the table.
+    pub fn set_string_table(&mut self, string_table: StringTable) {
+        self.string_table = Some(Rc::new(string_table));
+    }
+
+    /// Unloads the current `StringTable`, so the original text of the story is output again.
+    pub fn clear_string_table(&mut self) {
+        self.string_table = None;
+    }
+
+    /// Installs an `OutputSink`, which will be notified of all output as it is produced.
+    pub fn set_output_sink(&mut self, output_sink: Rc<RefCell<dyn OutputSink>>) {
+        self.output_sink = Some(output_sink);
+    }
+
+    /// Removes the current `OutputSink`, if any.
+    pub fn clear_output_sink(&mut self) {
+        self.output_sink = None;
+    }
+
+    /// Installs a `Tracer`, which will be notified of every object executed by the story.
+    pub fn set_tracer(&mut self, tracer: Rc<RefCell<dyn Tracer>>) {
+        self.tracer = Some(tracer);
+    }
+
+    /// Removes the current `Tracer`, if any.
+    pub fn clear_tracer(&mut self) {
+        self.tracer = None;
+    }
+
+    /// Starts collecting coverage, adding to the given `Coverage` (which may be empty, or loaded from
+    /// previous sessions).
+    pub fn enable_coverage(&mut self, coverage: Coverage) {
+        self.coverage = Some(coverage);
+    }
+
+    /// Stops collecting coverage, returning the coverage collected so far.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Produces a report of the coverage collected so far, or `None` if coverage is not enabled.
+    pub fn coverage_report(&self) -> Option<CoverageReport> {
+        self.coverage
+            .as_ref()
+            .map(|coverage| coverage.report(&self.main_container))
+    }
+
+    /// Starts profiling, adding to the given `Profiler`.
+    pub fn enable_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
+    /// Stops profiling, returning the profile collected so far.
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Sets the limits which the story will fail with a `LimitExceeded` error when exceeding. The
+    /// error is returned by `try_continue_story`, while `continue_story` panics with the
+    /// `LimitExceeded` as the payload.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    fn format_value(&self, value: &Value) -> String {
+        match &self.value_formatter {
+            Some(formatter) => formatter.format(value),
+            None => CanonicalValueFormatter { numeric_mode: self.numeric_mode }.format(value),
+        }
     }
 }
 
 // Story progression
 impl Story {
-    fn continue_single_step(&mut self) -> bool {
-        self.step();
+    /// Continues the story until the end of the next line of text, returning that line. The story
+    /// also stops when it reaches choices or the end of its content.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the story cannot continue, or if it runs out of content without reaching choices,
+    /// an `END` or a `DONE`. If it exceeds one of its `Limits`, it panics with the `LimitExceeded`
+    /// as the payload.
+    pub fn continue_story(&mut self) -> String {
+        match self.try_continue_story() {
+            Ok(text) => text,
+            Err(StoryError::LimitExceeded(error)) => std::panic::panic_any(error),
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Continues the story until it reaches choices or the end of its content, returning all of the
+    /// text produced.
+    pub fn continue_maximally(&mut self) -> String {
+        let mut text = String::new();
+        while self.can_continue() {
+            text.push_str(&self.continue_story());
+        }
+        text
+    }
+
+    /// Evaluates the ink function with this name, passing it the given arguments, and returns its
+    /// result (`None` if it returns nothing) along with any text it output. The function's text is
+    /// not added to the story's own output, so this may be called at any time, even while the
+    /// story is waiting for a choice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no function with this name, or if the function fails.
+    pub fn evaluate_function(&mut self, name: &str, args: &[Value]) -> (Option<Value>, String) {
+        let mut path = Path::default();
+        path.join(name.to_owned());
+        let function = match self.main_container.content_at_path(&path) {
+            Some(Object::Container(function)) => function,
+            _ => panic!("Function doesn't exist: '{}'", name),
+        };
+
+        // the function's output is kept apart from the story's, and isn't reported to the sink
+        let output_stream = std::mem::take(&mut self.output_stream);
+        let string_evaluation_depth = self.string_evaluation_depth;
+        let output_sink = self.output_sink.take();
+        let output_sink_writer = std::mem::take(&mut self.output_sink_writer);
+        self.reset_output();
+
+        self.push_call_stack(PushPopType::FunctionEvaluationFromGame);
+        self.set_current_pointer(Pointer::to_start_of_container(&function));
+        self.evaluation_stack.extend(args.iter().cloned().map(Object::Value));
+
+        let mut text = String::new();
+        while self.can_continue() {
+            text.push_str(&self.continue_story());
+        }
+
+        self.output_stream = output_stream;
+        self.string_evaluation_depth = string_evaluation_depth;
+        self.output_sink = output_sink;
+        self.output_sink_writer = output_sink_writer;
+
+        if self.current_element().push_pop_type != PushPopType::FunctionEvaluationFromGame {
+            panic!("Expected external function evaluation to be complete, but the call stack is in a {}", self.current_element().push_pop_type);
+        }
+        // any extra values (such as unused arguments) are cleaned up along with the result
+        let mut result = None;
+        while self.evaluation_stack.len() > self.current_element().evaluation_stack_size_when_called {
+            let object = self.evaluation_stack.pop().unwrap();
+            result = result.or(Some(object));
+        }
+        self.pop_call_stack(Some(PushPopType::FunctionEvaluationFromGame));
+
+        let result = match result {
+            Some(Object::Value(value)) => Some(value),
+            _ => None,
+        };
+        (result, text)
+    }
+
+    /// Continues the story for one line, like `continue_story`, but returns an error rather than
+    /// panicking when the story exceeds one of its `Limits` or runs out of content unexpectedly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the story cannot continue.
+    pub fn try_continue_story(&mut self) -> Result<String, StoryError> {
+        if !self.can_continue() {
+            panic!("Can't continue - should check can_continue before calling continue_story");
+        }
+
+        self.did_safe_exit = false;
+        self.reset_output();
+        self.steps_this_continue = 0;
+        loop {
+            let output_stream_ends_in_newline = match self.continue_single_step() {
+                Ok(output_stream_ends_in_newline) => output_stream_ends_in_newline,
+                Err(error) => {
+                    // the story stays where it stopped, so everything executed so far is kept
+                    self.discard_state_snapshot();
+                    return Err(error.into());
+                }
+            };
+            if output_stream_ends_in_newline || !self.can_continue() {
+                break;
+            }
+        }
+
+        // the story may have evaluated further than the end of the line
+        if let Some(snapshot) = self.state_snapshot.take() {
+            self.restore_state_snapshot(*snapshot);
+        }
+
+        self.output_sink_writer.end_line();
+        self.send_to_output_sink();
+        if !self.can_continue() && !self.current_choices.is_empty() {
+            if let Some(output_sink) = &self.output_sink {
+                output_sink.borrow_mut().on_choices(&self.current_choices());
+            }
+        }
+
+        let mut result = Ok(());
+        if !self.can_continue() {
+            if self.can_pop_thread() {
+                result = Err(StoryError::OutOfContent("Thread available to pop, threads should always be flat by the end of evaluation?".to_owned()));
+            } else if self.current_choices.is_empty() && !self.did_safe_exit && self.temporary_evaluation_container.is_none() {
+                let thread = self.current_thread();
+                let error = if thread.can_pop(Some(PushPopType::Tunnel)) {
+                    "unexpectedly reached end of content. Do you need a '->->' to return from a tunnel?"
+                } else if thread.can_pop(Some(PushPopType::Function)) {
+                    "unexpectedly reached end of content. Do you need a '~ return'?"
+                } else if !thread.can_pop(None) {
+                    "ran out of content. Do you need a '-> DONE' or '-> END'?"
+                } else {
+                    "unexpectedly reached end of content for unknown reason"
+                };
+                result = Err(StoryError::OutOfContent(error.to_owned()));
+            }
+        }
+        self.did_safe_exit = false;
+        result.map(|()| self.current_text())
+    }
+
+    /// Performs a single step, returning true once the output reaches the end of a line for sure.
+    fn continue_single_step(&mut self) -> Result<bool, LimitExceeded> {
+        self.step()?;
 
         if !self.can_continue() && self.current_element().push_pop_type != PushPopType::FunctionEvaluationFromGame {
             self.try_follow_default_invisible_choice();
         }
 
-        unimplemented!();
+        // strings being evaluated (e.g. for choices) are not part of the line
+        if self.in_string_evaluation() {
+            return Ok(false);
+        }
+
+        // after a newline, the story continues a little further in case the newline is removed by
+        // glue, and then returns to the end of the line once it is clear that it was not
+        if let Some(snapshot) = &self.state_snapshot {
+            let change = NewlineStateChange::between(
+                &snapshot.current_text(),
+                &self.current_text(),
+                snapshot.current_tags().len(),
+                self.current_tags().len(),
+            );
+            match change {
+                NewlineStateChange::ExtendedBeyondNewline => {
+                    let snapshot = self.state_snapshot.take().unwrap();
+                    self.restore_state_snapshot(*snapshot);
+                    return Ok(true);
+                }
+                NewlineStateChange::NewlineRemoved => self.discard_state_snapshot(),
+                NewlineStateChange::NoChange => {}
+            }
+        }
+
+        // the output is only sent to the sink once it's certain to be part of this line
+        if self.state_snapshot.is_none() {
+            self.send_to_output_sink();
+        }
+
+        if self.output_stream_ends_in_newline() {
+            if self.can_continue() {
+                if self.state_snapshot.is_none() {
+                    self.state_snapshot = Some(Box::new(self.clone()));
+                }
+            } else {
+                self.discard_state_snapshot();
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn restore_state_snapshot(&mut self, snapshot: Story) {
+        *self = snapshot;
+    }
+
+    /// Drops the snapshot once it's clear that the story won't return to it, so everything done
+    /// since it was taken is part of the current line.
+    fn discard_state_snapshot(&mut self) {
+        self.state_snapshot = None;
+        if let Some(tracer) = &self.tracer {
+            let mut tracer = tracer.borrow_mut();
+            for trace in self.pending_traces.drain(..) {
+                tracer.trace(&trace.event());
+            }
+        }
+        self.pending_traces.clear();
+    }
+
+    /// Sends the output which has been written since the last time to the output sink.
+    fn send_to_output_sink(&mut self) {
+        if let Some(output_sink) = &self.output_sink {
+            self.output_sink_writer.send(&mut *output_sink.borrow_mut());
+        }
+    }
+
+    /// Prepares to execute objects one `step` at a time, outside of `continue_story` (such as from
+    /// the `Debugger`). As at the start of a `continue`, the step limit counts from zero again,
+    /// and a line which has been completed is sent to the output sink and cleared.
+    pub(crate) fn begin_steps(&mut self) {
+        self.steps_this_continue = 0;
+        if self.output_stream_ends_in_newline() {
+            self.output_sink_writer.end_line();
+            self.send_to_output_sink();
+            self.reset_output();
+        }
+    }
+
+    pub(crate) fn step(&mut self) -> Result<(), LimitExceeded> {
+        if self.profiler.is_none() {
+            self.perform_step();
+        } else {
+            self.profile_step();
+        }
+
+        self.steps_this_continue += 1;
+        let error = self.limits.check(
+            self.steps_this_continue,
+            self.call_stack_depth(),
+            self.evaluation_stack.len(),
+            self.output_stream.len(),
+            || self.current_thread().previous_pointer.path().unwrap_or_default(),
+        );
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn profile_step(&mut self) {
+        // each frame is attributed to the knot it's in, or to the root if it's not in a knot
+        let stack = self.current_thread().elements
+            .iter()
+            .map(|element| element.current_pointer
+                .path()
+                .map(|path| path.parts.into_iter().take(1).filter(|part| matches!(part, Component::Name(..))).collect())
+                .map(|parts| Path { parts, is_relative: false })
+                .unwrap_or_default()
+            )
+            .collect();
+        self.profiler.as_mut().unwrap().begin_step(stack);
+        let start = Instant::now();
+        self.perform_step();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_step(start.elapsed());
+        }
     }
 
-    fn step(&mut self) {
+    fn perform_step(&mut self) {
         let mut pointer = self.current_pointer();
         if pointer.is_null() { return; }
 
@@ -212,6 +734,19 @@ impl Story {
         }
 
         let mut current_obj = pointer.resolve();
+        if let (Some(tracer), Some(object)) = (&self.tracer, &current_obj) {
+            let trace = PendingTrace {
+                path: pointer.path().unwrap_or_default(),
+                object: object.clone(),
+                evaluation_stack_depth: self.evaluation_stack.len(),
+                call_stack_depth: self.call_stack_depth(),
+            };
+            if self.state_snapshot.is_some() {
+                self.pending_traces.push(trace);
+            } else {
+                tracer.borrow_mut().trace(&trace.event());
+            }
+        }
         self.set_current_pointer(pointer);
 
         let is_logic_or_flow_control = self.perform_logic_and_flow_control(current_obj.clone());
@@ -236,22 +771,22 @@ impl Story {
         }
 
         if should_add_to_stream {
-            if let Some(var_pointer) = current_obj.as_ref().and_then(TryAsRef::<VariablePointer>::try_as_ref).cloned() {
-                let var_pointer = match var_pointer.context {
-                    VariableContext::Unknown => {
+            if let Some(mut object) = current_obj.clone() {
+                if let Some(var_pointer) = TryAsRef::<VariablePointer>::try_as_ref(&object).cloned() {
+                    if var_pointer.context == VariableContext::Unknown {
                         let context = self.context_for_variable_named(&var_pointer.name);
-                        VariablePointer {
-                            name: var_pointer.name.clone(),
+                        object = Object::Value(Value::VariablePointer(VariablePointer {
+                            name: var_pointer.name,
                             context,
-                        }
+                        }));
                     }
-                    _ => var_pointer.clone(),
-                };
+                }
 
                 if self.current_element().in_expression_evaluation {
-                    self.evaluation_stack.push(Object::Value(Value::VariablePointer(var_pointer)));
+                    self.evaluation_stack.push(object);
                 } else {
-                    self.output_stream.push(Object::Value(Value::VariablePointer(var_pointer)));
+                    let object = self.localize(object);
+                    self.push_to_output_stream(object);
                 }
             }
         }
@@ -300,24 +835,18 @@ impl Story {
                     _ => panic!("Attempted to divert to a variable target, but variable {} contained a non-divert target value {:?}", variable, value),
                 }
             },
-            DivertTarget::External { path, args } => {},
-            DivertTarget::Path(path) => self.diverted_pointer = self.pointer_to_path(path, None),
+            DivertTarget::External { path: name, args } => {
+                self.call_external_function(name, *args as usize);
+                return true;
+            },
+            DivertTarget::Path(path) => {
+                let path = self.resolve_path(path);
+                self.diverted_pointer = self.pointer_at_path(&path);
+            },
         }
 
         if divert.pushes_to_stack {
-            let element = Element {
-                current_pointer: self.current_pointer(),
-                in_expression_evaluation: false,
-                temporary_variables: HashMap::default(),
-                push_pop_type: divert.stack_push_type,
-                evaluation_stack_size_when_called: 0,
-                function_start_in_output_stream: self.output_stream.len(),
-            };
-            self.threads
-                .last_mut()
-                .unwrap()
-                .elements
-                .push(element);
+            self.push_call_stack(divert.stack_push_type);
         }
 
         if self.diverted_pointer.is_none() && !divert.is_external() {
@@ -333,13 +862,16 @@ impl Story {
             ControlCommand::EvalStart => self.current_element_mut().in_expression_evaluation = true,
             ControlCommand::EvalEnd => self.current_element_mut().in_expression_evaluation = false,
             ControlCommand::EvalOutput => {
-                if let Some(output) = self.evaluation_stack.pop() {
-                    if output != Object::Void {
-                        self.output_stream.push(output);
+                match self.evaluation_stack.pop() {
+                    None | Some(Object::Void) => {}
+                    Some(Object::Value(value)) => {
+                        let text = self.format_value(&value);
+                        self.push_to_output_stream(Object::Value(Value::String(text)));
                     }
+                    Some(output) => self.push_to_output_stream(output),
                 }
             }
-            ControlCommand::Duplicate => self.evaluation_stack.push(self.evaluation_stack.first().unwrap().clone()),
+            ControlCommand::Duplicate => self.evaluation_stack.push(self.evaluation_stack.last().unwrap().clone()),
             ControlCommand::PopEvaluatedValue => { self.evaluation_stack.pop(); }
             | ControlCommand::PopFunction
             | ControlCommand::PopTunnel => {
@@ -356,35 +888,36 @@ impl Story {
                 if self.try_exit_function_evaluation_from_game() {
                     return true;
                 }
+                if !self.current_thread().can_pop(None) {
+                    panic!("Expected end of flow (-> END or choice), but instead attempted to pop {}", pop_type);
+                }
                 if self.current_element().push_pop_type != pop_type {
                     panic!("Expected to pop {}, but instead attempted to pop {}", self.current_element().push_pop_type, pop_type);
                 }
-                if self.current_thread().elements.is_empty() {
-                    panic!("Expected end of flow, but instead attempted to pop {}", pop_type);
-                }
 
                 self.pop_call_stack(None);
+                if let Some(path) = override_path {
+                    self.diverted_pointer = self.pointer_at_path(&path);
+                }
             }
             ControlCommand::BeginString => {
-                assert!(!self.current_element().in_expression_evaluation, "Error processing control command: Must be in expression evaluation mode to begin a string");
+                assert!(self.current_element().in_expression_evaluation, "Error processing control command: Must be in expression evaluation mode to begin a string");
                 self.current_element_mut().in_expression_evaluation = false;
-                self.output_stream.push(Object::ControlCommand(ControlCommand::BeginString));
+                self.push_to_output_stream(Object::ControlCommand(ControlCommand::BeginString));
+                self.string_evaluation_depth += 1;
             }
             ControlCommand::EndString => {
-                let mut string_content = VecDeque::new();
-                let mut output_count_consumed = 0;
-                for i in (0..=self.output_stream.len()).rev() {
-                    let obj = &self.output_stream[i];
-                    output_count_consumed += 1;
-                    if TryAsRef::<ControlCommand>::try_as_ref(obj).is_some() { break }
-                    if let Some(string) = TryAsRef::<String>::try_as_ref(obj) {
-                        string_content.push_front(string.as_str());
-                    }
-                }
-                let string: String = string_content.into_iter().collect();
-                self.output_stream.truncate(self.output_stream.len() - output_count_consumed);
-                self.output_stream.push(Object::Value(Value::String(string)));
+                let begin = self.output_stream
+                    .iter()
+                    .rposition(|obj| obj == &Object::ControlCommand(ControlCommand::BeginString))
+                    .expect("Error processing control command: Found end of string without a beginning");
+                let string: String = self.output_stream
+                    .drain(begin..)
+                    .filter_map(|obj| TryInto::<String>::try_into(obj).ok())
+                    .collect();
+                self.string_evaluation_depth -= 1;
                 self.current_element_mut().in_expression_evaluation = true;
+                self.evaluation_stack.push(Object::Value(Value::String(string)));
             }
             ControlCommand::ChoiceCount => {
                 let count = self.current_choices.len() as i64;
@@ -401,18 +934,12 @@ impl Story {
                     None => panic!("Expected to find a divert target value to check turns since/read count, but found {:?}", object),
                 };
 
-                let count = if command == ControlCommand::TurnsSince {
-                    self.visit_counts
-                        .get(target)
-                        .cloned()
-                        .map(|u| u as i64)
-                        .unwrap_or(0) // default is zero because never visited this one
-                } else {
-                    self.turn_indices
-                        .get(target)
-                        .cloned()
-                        .map(|u| u as i64)
-                        .unwrap_or(-1) // -1 to indicate never reached before
+                // content which cannot be found has never been visited
+                let count = match (self.container_at_path(target), command) {
+                    (Some(container), ControlCommand::TurnsSince) => self.turns_since_container(&container),
+                    (Some(container), _) => self.visit_count_for_container(&container) as i64,
+                    (None, ControlCommand::TurnsSince) => -1,
+                    (None, _) => 0,
                 };
 
                 self.evaluation_stack.push(Object::Value(Value::Int(count)));
@@ -420,26 +947,24 @@ impl Story {
             ControlCommand::Random => {
                 let max_int = self.evaluation_stack.pop().and_then(|value| TryInto::<i64>::try_into(value).ok()).expect("Invalid parameter for max value of RANDOM");
                 let min_int = self.evaluation_stack.pop().and_then(|value| TryInto::<i64>::try_into(value).ok()).expect("Invalid parameter for min value of RANDOM");
-                let result_seed = self.story_seed + self.previous_random;
-                let mut rng = Pcg64::seed_from_u64(result_seed as u64);
-                let result = rng.gen_range(min_int, max_int + 1);
-                self.previous_random = result as u64;
+                let (min_int, max_int) = (self.numeric_mode.int(min_int), self.numeric_mode.int(max_int));
+                let range = self.numeric_mode.int(max_int - min_int + 1);
+                if range <= 0 {
+                    panic!("RANDOM was called with minimum as {} and maximum as {}. The maximum must be larger", min_int, max_int);
+                }
+                let next_random = self.next_random(self.story_seed.wrapping_add(self.previous_random));
+                let result = self.numeric_mode.int(next_random % range + min_int);
+                self.previous_random = next_random as u64;
                 self.evaluation_stack.push(Object::Value(Value::Int(result)));
             }
             ControlCommand::SeedRandom => {
                 let seed = self.evaluation_stack.pop().and_then(|value| TryInto::<i64>::try_into(value).ok()).expect("Integer value was not provided to SEED_RANDOM");
-                self.story_seed = seed as u64;
-                self.previous_random = 0;
+                self.set_seed(seed as u64);
                 self.evaluation_stack.push(Object::Void);
             }
             ControlCommand::VisitIndex => {
-                let pointer = self.current_pointer();
-                let object = Object::Container(pointer.container().unwrap());
-                let path = object.path();
-                let visit_count = self.visit_counts
-                    .get(&path)
-                    .cloned()
-                    .unwrap_or(0);
+                let container = self.current_pointer().container().unwrap();
+                let visit_count = self.visit_count_for_container(&container);
                 self.evaluation_stack.push(Object::Value(Value::Int(visit_count as i64 - 1)));
             }
             ControlCommand::SequenceShuffleIndex => {
@@ -464,7 +989,8 @@ impl Story {
                 let list_definition = self.list_definitions.list_definition_by_name(&list_name).expect(&format!("No list definition found named {}", list_name));
                 match list_definition.item_with_value(int) {
                     Some(entry) => self.evaluation_stack.push(Object::Value(Value::List(List::of_single_value(entry.clone())))),
-                    None => self.evaluation_stack.push(Object::Value(Value::List(List::default()))),
+                    // an empty list still knows which list it is from
+                    None => self.evaluation_stack.push(Object::Value(Value::List(List::default().with_empty_origins(&[list_name])))),
                 }
             }
             ControlCommand::ListRange => {
@@ -479,10 +1005,10 @@ impl Story {
                 if list.is_empty() {
                     self.evaluation_stack.push(Object::Value(Value::List(List::default())));
                 } else {
-                    let result_seed = self.story_seed + self.previous_random;
-                    let mut random = Pcg64::seed_from_u64(result_seed);
-                    let index = random.gen_range(0, list.len() as u64);
+                    let next_random = self.next_random(self.story_seed.wrapping_add(self.previous_random));
+                    let index = next_random as u64 % list.len() as u64;
                     let entry = list.items.iter().nth(index as usize).cloned().unwrap();
+                    self.previous_random = next_random as u64;
                     self.evaluation_stack.push(Object::Value(Value::List(List::of_single_value(entry))));
                 }
             }
@@ -500,7 +1026,9 @@ impl Story {
     fn perform_variable_reference(&mut self, reference: Rc<VariableReference>) -> bool {
         match &*reference {
             VariableReference::PathForCount(path) => {
-                let count = self.visit_counts.get(path).cloned().unwrap_or(0);
+                let count = self.container_at_path(path)
+                    .map(|container| self.visit_count_for_container(&container))
+                    .unwrap_or_else(|| panic!("Read count target not found: {}", path));
                 self.evaluation_stack.push(Object::Value(Value::Int(count as i64)));
             },
             VariableReference::Variable(name) => {
@@ -512,14 +1040,17 @@ impl Story {
     }
 
     fn perform_native_function_call(&mut self, call: Rc<NativeFunctionCall>) -> bool {
-        let new_evaluation_stack = self.evaluation_stack.split_off(call.number_of_parameters());
-        let params = std::mem::replace(&mut self.evaluation_stack, new_evaluation_stack);
+        let first_param = self.evaluation_stack.len() - call.number_of_parameters();
+        let params = self.evaluation_stack.split_off(first_param);
         let values = params
             .into_iter()
             .map(TryInto::try_into)
             .map(|value| value.unwrap())
             .collect();
-        let result = call.call(&self.list_definitions, values);
+        let result = call.call(&self.list_definitions, self.numeric_mode, &|value: &Value| self.format_value(value), values);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_native_call();
+        }
         self.evaluation_stack.push(Object::Value(result));
         true
     }
@@ -542,18 +1073,17 @@ impl Story {
             self.evaluation_stack.pop().unwrap().try_into().unwrap()
         } else { String::new() };
 
-        if choice_point.once_only {
-            let visit_count = self.visit_counts.get(&choice_point.path_on_choice).cloned().unwrap_or(0);
-            if visit_count > 0 {
-                show_choice = false;
-            }
+        let target = self.container_at_path(&choice_point.path_on_choice)
+            .unwrap_or_else(|| panic!("Choice target not found: {}", choice_point.path_on_choice));
+        if choice_point.once_only && self.visit_count_for_container(&target) > 0 {
+            show_choice = false;
         }
 
         if !show_choice { return None } // NOTE: have to always evaluate everything, otherwise the values will be on the stacks
 
         let choice = Choice::new(
-            (start_text + &choice_only_text).trim().to_string(),
-            choice_point.path_on_choice.clone(),
+            (start_text + &choice_only_text).trim_matches(|ch| ch == ' ' || ch == '\t').to_string(),
+            Self::count_path(&target),
             choice_point.is_invisible_default,
             self.fork_thread(),
         );
@@ -594,19 +1124,50 @@ impl Story {
 
     fn try_follow_default_invisible_choice(&mut self) -> bool {
         let all_choices = &self.current_choices;
-        let mut invisible_choices = all_choices.iter().filter(|choice| choice.is_invisible_default).collect::<Vec<_>>();
+        let invisible_choices = all_choices.iter().filter(|choice| choice.is_invisible_default).collect::<Vec<_>>();
         // can only follow it automatically if it's the only choice
         if invisible_choices.is_empty() || all_choices.len() > invisible_choices.len() {
             return false;
         }
 
-        let choice = invisible_choices.pop().unwrap().clone();
+        let choice = invisible_choices[0].clone();
         self.set_current_thread(choice.thread_at_generation.clone());
+        // the thread may be needed again if the story returns to a snapshot taken before this
+        if self.state_snapshot.is_some() {
+            let thread = self.fork_thread();
+            self.set_current_thread(thread);
+        }
         self.choose_path(&choice.target_path, false);
         return true;
     }
 
+    /// Chooses one of the `current_choices`, by its index in that list.
+    pub fn choose_choice_index(&mut self, index: usize) {
+        let choice = match self.current_choices().get(index) {
+            Some(choice) => choice.clone(),
+            None => panic!("Choice out of range: {} (there are {} choices)", index, self.current_choices().len()),
+        };
+        self.set_current_thread(choice.thread_at_generation.clone());
+        self.choose_path(&choice.target_path, true);
+    }
+
+    /// Jumps to the content at the given path (e.g. `"knot.stitch"`), abandoning the call stack and
+    /// any current choices.
+    pub fn choose_path_string(&mut self, path: &str) {
+        let path = match path.parse() {
+            Ok(path) => path,
+            Err(error) => panic!("{}", error),
+        };
+        if self.pointer_at_path(&path).is_none() {
+            panic!("Content at path not found: {}", path);
+        }
+        self.threads = vec![Thread::new(PushPopType::Tunnel, self.start_of_root.clone())];
+        self.thread_counter = 0;
+        self.choose_path(&path, true);
+    }
+
     fn choose_path(&mut self, path: &Path, incrementing_turn_index: bool) {
+        self.steps_this_continue = 0;
         self.set_chosen_path(path, incrementing_turn_index);
         self.visit_changed_containers_due_to_divert();
     }
@@ -636,15 +1197,202 @@ impl Story {
     }
 }
 
+// Inspection
+impl Story {
+    /// The path to the content that will be executed next, or `None` if the story cannot continue.
+    pub fn current_path(&self) -> Option<Path> {
+        self.next_object().and_then(|(pointer, _)| pointer.path())
+    }
+
+    /// Renders the story's content as indented pseudo-assembly. See `Disassembly`.
+    pub fn disassemble(&self) -> String {
+        Disassembly::new(&self.main_container).to_string()
+    }
+
+    /// Builds a map of the knots and stitches of this story, and the connections between them.
+    pub fn graph(&self) -> StoryGraph {
+        StoryGraph::build(&self.main_container)
+    }
+
+    /// The paths of the knots of this story, excluding functions, in the order they were written.
+    pub fn knots(&self) -> Vec<Path> {
+        let graph = self.graph();
+        let functions = self.functions_in(&graph);
+        Self::knot_paths(&graph)
+            .filter(|path| !functions.contains(path))
+            .collect()
+    }
+
+    /// The paths (`knot.stitch`) of the stitches of this story, in the order they were written.
+    pub fn stitches(&self) -> Vec<Path> {
+        self.graph().knots
+            .into_iter()
+            .flat_map(|knot| {
+                let name = knot.name;
+                knot.stitches
+                    .into_iter()
+                    .map(move |stitch| Path { parts: vec![Component::Name(name.clone()), Component::Name(stitch)], is_relative: false })
+            })
+            .collect()
+    }
+
+    /// The paths of the functions of this story. A knot is considered a function if it is called
+    /// as a function anywhere in the story, or if its content ends with a `~ return`.
+    pub fn functions(&self) -> Vec<Path> {
+        self.functions_in(&self.graph())
+    }
+
+    fn functions_in(&self, graph: &StoryGraph) -> Vec<Path> {
+        Self::knot_paths(graph)
+            .filter(|path| {
+                let called = graph.edges
+                    .iter()
+                    .any(|edge| edge.kind == EdgeKind::Function && &edge.to == path);
+                let returns = match self.main_container.content_at_path(path) {
+                    Some(Object::Container(knot)) => matches!(knot.content.last(), Some(Object::ControlCommand(ControlCommand::PopFunction))),
+                    _ => false,
+                };
+                called || returns
+            })
+            .collect()
+    }
+
+    fn knot_paths(graph: &StoryGraph) -> impl Iterator<Item = Path> + '_ {
+        graph.knots
+            .iter()
+            .map(|knot| Path { parts: vec![Component::Name(knot.name.clone())], is_relative: false })
+    }
+
+    /// Whether there is any content at the given path (e.g. `"knot.stitch"`).
+    pub fn has_path(&self, path: &str) -> bool {
+        match path.parse() {
+            Ok(path) => self.main_container.content_at_path(&path).is_some(),
+            Err(..) => false,
+        }
+    }
+
+    /// Checks this story for likely mistakes. See `lint`.
+    pub fn lint(&self) -> Vec<Lint> {
+        lint(&self.main_container, &self.list_definitions)
+    }
+
+    /// The values on the evaluation stack, from the bottom of the stack to the top.
+    pub fn evaluation_stack(&self) -> &[Object] {
+        &self.evaluation_stack
+    }
+
+    /// The temporary variables of the current call stack frame.
+    pub fn temporary_variables(&self) -> &HashMap<String, Object> {
+        &self.current_element().temporary_variables
+    }
+
+    /// The number of frames in the call stack of the current thread.
+    pub fn call_stack_depth(&self) -> usize {
+        self.current_thread().elements.len()
+    }
+
+    /// All the threads that currently exist. The last one is the thread being executed.
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
+    }
+
+    /// Finds the object that the next `step` will execute, along with a pointer to it, without
+    /// actually executing it.
+    pub(crate) fn next_object(&self) -> Option<(Pointer, Object)> {
+        let pointer = self.current_pointer();
+        if pointer.is_null() { return None; }
+        Self::first_object_from(pointer)
+    }
+
+    /// The path to the first object executed when the story goes to the content at this path.
+    pub(crate) fn first_object_path(&self, path: &Path) -> Option<Path> {
+        Self::first_object_from(self.pointer_at_path(path)?).and_then(|(pointer, _)| pointer.path())
+    }
+
+    /// Finds the object at the pointer, descending into the start of containers.
+    fn first_object_from(mut pointer: Pointer) -> Option<(Pointer, Object)> {
+        while let Some(container) = pointer.resolve().and_then(|obj| TryAsRef::<Rc<Container>>::try_as_ref(&obj).cloned()) {
+            if container.is_empty() {
+                break;
+            }
+            pointer = Pointer::to_start_of_container(&container);
+        }
+
+        let object = pointer.resolve()?;
+        Some((pointer, object))
+    }
+}
+
+// Read counts
+impl Story {
+    /// The number of times the content at the given path (e.g. `"knot.stitch"`) has been visited.
+    pub fn visit_count_at_path(&self, path: &str) -> usize {
+        self.visit_count_for_container(&self.container_named_by(path))
+    }
+
+    /// The number of turns since the content at the given path was last visited, or -1 if it
+    /// has never been visited.
+    pub fn turns_since(&self, path: &str) -> i64 {
+        self.turns_since_container(&self.container_named_by(path))
+    }
+
+    /// The number of choices that have been made so far.
+    pub fn current_turn_index(&self) -> usize {
+        self.current_turn_index
+    }
+
+    /// Finds the container at a path written by a user.
+    fn container_named_by(&self, path: &str) -> Rc<Container> {
+        let path = match path.parse() {
+            Ok(path) => path,
+            Err(error) => panic!("{}", error),
+        };
+        match self.container_at_path(&path) {
+            Some(container) => container,
+            None => panic!("Content at path not found: {}", path),
+        }
+    }
+
+    /// Finds the container at a path found in the content being executed, which may be relative to
+    /// the current content.
+    fn container_at_path(&self, path: &Path) -> Option<Rc<Container>> {
+        match self.main_container.content_at_path(&self.resolve_path(path))? {
+            Object::Container(container) => Some(container),
+            _ => None,
+        }
+    }
+
+    /// The path that visits to a container are recorded under. A container may be reached by many
+    /// paths (by name or by index, relative or absolute), so counts are always recorded under the
+    /// path built from the root down to the container itself.
+    fn count_path(container: &Rc<Container>) -> Path {
+        Object::Container(container.clone()).path()
+    }
+
+    fn visit_count_for_container(&self, container: &Rc<Container>) -> usize {
+        self.visit_counts.get(&Self::count_path(container)).cloned().unwrap_or(0)
+    }
+
+    fn turns_since_container(&self, container: &Rc<Container>) -> i64 {
+        self.turn_indices
+            .get(&Self::count_path(container))
+            .map(|&turn_index| self.current_turn_index as i64 - turn_index as i64)
+            .unwrap_or(-1) // -1 to indicate never reached before
+    }
+}
+
 // Story helpers
 impl Story {
     fn visit_container(&mut self, container: &Rc<Container>, at_start: bool) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(Self::count_path(container));
+        }
         if !container.counting_at_start_only || at_start {
             if container.visits_should_be_counted {
-                *self.visit_counts.entry(Object::Container(container.clone()).path()).or_default() += 1;
+                *self.visit_counts.entry(Self::count_path(container)).or_default() += 1;
             }
             if container.turn_index_should_be_counted {
-                self.turn_indices.insert(Object::Container(container.clone()).path(), self.current_turn_index);
+                self.turn_indices.insert(Self::count_path(container), self.current_turn_index);
             }
         }
     }
@@ -678,13 +1426,26 @@ impl Story {
             None => return,
         };
 
-        let mut current_container = current_pointer.container();
+        // an empty container resolves to itself
+        let mut current_container = match &current_child_of_container {
+            Object::Container(container) if current_pointer.container().is_some_and(|parent| Rc::ptr_eq(&parent, container)) => {
+                current_child_of_container.parent().as_ref().and_then(TryAsRef::<Rc<Container>>::try_as_ref).cloned()
+            }
+            _ => current_pointer.container(),
+        };
+        let mut all_children_entered_at_start = true;
         while let Some(container) = current_container {
-            if prev_containers.iter().any(|prev| Rc::ptr_eq(prev, &container)) || container.counting_at_start_only {
+            if prev_containers.iter().any(|prev| Rc::ptr_eq(prev, &container)) && !container.counting_at_start_only {
                 break;
             }
-            let entering_at_start = !container.content.is_empty()
+            // only entering the first leaf directly counts as entering each of its ancestors at the
+            // start
+            let entering_at_start = all_children_entered_at_start
+                && !container.content.is_empty()
                 && &current_child_of_container == container.content.first().unwrap();
+            if !entering_at_start {
+                all_children_entered_at_start = false;
+            }
             self.visit_container(&container, entering_at_start);
             current_container = container.parent
                 .as_ref()
@@ -699,7 +1460,7 @@ impl Story {
     // another sketchy pair of very similarly named functions... but this one seems to do something
     // different
     fn pointer_at_path(&self, path: &Path) -> Option<Pointer> {
-        if path.is_empty() { return None }
+        if path.is_empty() { return Some(Pointer::to_start_of_container(&self.main_container)) }
 
         match path.parts.last().unwrap() {
             Component::Index(i) => {
@@ -722,19 +1483,20 @@ impl Story {
         }
     }
 
-    fn pointer_to_path(&self, path: &Path, relative_to: Option<Object>)-> Option<Pointer> {
-        if path.is_empty() { return None }
-        if path.is_relative {
-            match relative_to {
-                Some(object) => return object.resolve_path(path).as_ref().map(Pointer::to),
-                None => panic!("Cannot resolve relative path with no provided root"),
-            }
+    /// Resolves a path found in the content being executed, which may be relative to it, to an
+    /// absolute path.
+    fn resolve_path(&self, path: &Path) -> Path {
+        if !path.is_relative {
+            return path.clone();
+        }
+        match self.current_pointer().path() {
+            Some(current_path) => current_path.appending(path),
+            None => panic!("Cannot resolve relative path {} with no current content", path),
         }
-        self.main_container.content_at_path(path).as_ref().map(Pointer::to)
     }
 
     fn increment_content_pointer(&mut self) -> bool {
-        let mut successful_increment = false;
+        let mut successful_increment = true;
         let mut pointer = self.current_pointer();
         // NOTE: some reason we just assume everything is not null here...
         pointer.increment_index();
@@ -765,22 +1527,220 @@ impl Story {
         successful_increment
     }
 
+    fn push_to_output_stream(&mut self, object: Object) {
+        if !self.in_string_evaluation() && TryAsRef::<String>::try_as_ref(&object).is_some_and(|text| text.contains('\n')) {
+            self.steps_this_continue = 0;
+        }
+        if let Some(text) = TryAsRef::<String>::try_as_ref(&object) {
+            if let Some(texts) = split_head_tail_whitespace(text) {
+                for text in texts {
+                    self.push_to_output_stream_individual(Object::Value(Value::String(text)));
+                }
+                return;
+            }
+        }
+        self.push_to_output_stream_individual(object);
+    }
+
+    fn push_to_output_stream_individual(&mut self, object: Object) {
+        if let Object::Glue(..) = object {
+            self.trim_newlines_from_output_stream();
+        } else if let Some(text) = TryAsRef::<String>::try_as_ref(&object) {
+            // whitespace is trimmed from the start of a function's output, and after glue
+            let mut function_trim_index = match self.current_element().push_pop_type {
+                PushPopType::Function => self.current_element().function_start_in_output_stream,
+                _ => None,
+            };
+            let mut glue_trim_index = None;
+            for (i, object) in self.output_stream.iter().enumerate().rev() {
+                match object {
+                    Object::Glue(..) => {
+                        glue_trim_index = Some(i);
+                        break;
+                    }
+                    Object::ControlCommand(ControlCommand::BeginString) => {
+                        // a string being built inside the function is trimmed separately
+                        if function_trim_index.is_some_and(|index| i >= index) {
+                            function_trim_index = None;
+                        }
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            if glue_trim_index.is_some() || function_trim_index.is_some() {
+                if is_newline(text) {
+                    return;
+                }
+                if is_non_whitespace(text) {
+                    if glue_trim_index.is_some() {
+                        self.remove_existing_glue();
+                    }
+                    if function_trim_index.is_some() {
+                        for element in self.current_thread_mut().elements.iter_mut().rev() {
+                            if element.push_pop_type != PushPopType::Function { break }
+                            element.function_start_in_output_stream = None;
+                        }
+                    }
+                }
+            } else if is_newline(text) && (self.output_stream_ends_in_newline() || !self.output_stream_contains_content()) {
+                return;
+            }
+        }
+        if self.output_sink.is_some() && !self.in_string_evaluation() {
+            match &object {
+                Object::Value(Value::String(text)) => self.output_sink_writer.write_text(text),
+                Object::Tag(tag) => self.output_sink_writer.write_tag(tag.text()),
+                _ => {}
+            }
+        }
+        self.output_stream.push(object);
+    }
+
+    /// Removes the newlines (and any whitespace after them) from the end of the output stream, as
+    /// glue joins the following text onto the current line.
+    fn trim_newlines_from_output_stream(&mut self) {
+        let mut remove_whitespace_from = None;
+        for (i, object) in self.output_stream.iter().enumerate().rev() {
+            match object {
+                Object::ControlCommand(..) => break,
+                Object::Value(Value::String(text)) if is_non_whitespace(text) => break,
+                Object::Value(Value::String(text)) if is_newline(text) => remove_whitespace_from = Some(i),
+                _ => {}
+            }
+        }
+        if let Some(index) = remove_whitespace_from {
+            let tail = self.output_stream.split_off(index);
+            self.output_stream.extend(tail.into_iter().filter(|object| TryAsRef::<String>::try_as_ref(object).is_none()));
+            self.rewind_output_sink_writer();
+        }
+    }
+
+    /// Removes the glue from the end of the output stream, once it has joined some text on.
+    fn remove_existing_glue(&mut self) {
+        for i in (0..self.output_stream.len()).rev() {
+            match self.output_stream[i] {
+                Object::Glue(..) => { self.output_stream.remove(i); }
+                Object::ControlCommand(..) => break,
+                _ => {}
+            }
+        }
+    }
+
+    fn output_stream_ends_in_newline(&self) -> bool {
+        for object in self.output_stream.iter().rev() {
+            match object {
+                Object::ControlCommand(..) => break,
+                Object::Value(Value::String(text)) if is_newline(text) => return true,
+                Object::Value(Value::String(text)) if is_non_whitespace(text) => break,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn output_stream_contains_content(&self) -> bool {
+        self.output_stream
+            .iter()
+            .any(|object| TryAsRef::<String>::try_as_ref(object).is_some())
+    }
+
+    fn reset_output(&mut self) {
+        self.output_stream.clear();
+        self.string_evaluation_depth = 0;
+        self.output_sink_writer = OutputSinkWriter::default();
+    }
+
+    /// Brings the output sink's writer up to date after whitespace has been removed from the end of
+    /// the output stream.
+    fn rewind_output_sink_writer(&mut self) {
+        if self.output_sink.is_none() || self.in_string_evaluation() {
+            return;
+        }
+        let mut trailing_whitespace = vec![];
+        let mut at_start = true;
+        for text in self.output_stream.iter().rev().filter_map(TryAsRef::<String>::try_as_ref) {
+            let content_end = text.trim_end_matches([' ', '\t', '\n']).len();
+            trailing_whitespace.push(&text[content_end..]);
+            if content_end > 0 {
+                at_start = false;
+                break;
+            }
+        }
+        let trailing_whitespace = trailing_whitespace.into_iter().rev().collect::<String>();
+        self.output_sink_writer.rewind(&trailing_whitespace, at_start);
+    }
+
+    /// Whether the output stream is currently being used to build a string (between `BeginString`
+    /// and `EndString`), in which case the output is not actually part of the story's text.
+    fn in_string_evaluation(&self) -> bool {
+        self.string_evaluation_depth > 0
+    }
+
+    /// Substitutes the text from the loaded string table for a string that is about to be output,
+    /// if there is a translation for it.
+    fn localize(&self, object: Object) -> Object {
+        let string_table = match &self.string_table {
+            Some(string_table) => string_table,
+            None => return object,
+        };
+        match TryAsRef::<String>::try_as_ref(&object) {
+            Some(text) if !text.trim().is_empty() => {}
+            _ => return object,
+        }
+        StringTable::key_at(&self.current_pointer())
+            .and_then(|key| string_table.get(&key))
+            .map(|text| Object::Value(Value::String(text.to_owned())))
+            .unwrap_or(object)
+    }
+
     fn trim_whitespace_from_function_end(&mut self) {
-        let function_start_point = self.current_element().function_start_in_output_stream;
-        for i in (usize::max(function_start_point, 0)..=self.output_stream.len()).rev() {
+        let function_start_point = self.current_element().function_start_in_output_stream.unwrap_or(0);
+        for i in (function_start_point..self.output_stream.len()).rev() {
             let obj = &self.output_stream[i];
             if let Some(text) = TryAsRef::<String>::try_as_ref(obj) {
-                // NB: may differ from original implementation behaviour in this line
-                //     A string value containing some combination of both spaces/tabs and newline
-                //     will not be classified as whitespace in the original implementation, but
-                //     will here
-                if !text.trim().is_empty() { // if some of the string remains, it's not whitespace, so we can stop trimming
+                if is_non_whitespace(text) {
                     break;
                 }
                 self.output_stream.remove(i);
-                self.output_stream_dirty();
+            } else if TryAsRef::<ControlCommand>::try_as_ref(obj).is_some() {
+                break;
             }
         }
+        self.rewind_output_sink_writer();
+    }
+
+    /// Calls the external function bound with `ink_external!`, or the ink function with the same
+    /// name if there is no binding.
+    fn call_external_function(&mut self, name: &str, number_of_arguments: usize) {
+        let external = inventory::iter::<ExternalFunction>
+            .into_iter()
+            .find(|external| external.name == name);
+        let external = match external {
+            Some(external) => external,
+            None => {
+                let mut fallback = Path::default();
+                fallback.join(name.to_owned());
+                match self.pointer_at_path(&fallback) {
+                    Some(pointer) => {
+                        self.push_call_stack(PushPopType::Function);
+                        self.diverted_pointer = Some(pointer);
+                        return;
+                    }
+                    None => panic!("Trying to call EXTERNAL function '{}' which has not been bound, and has no fallback ink function", name),
+                }
+            }
+        };
+
+        let first_argument = self.evaluation_stack.len() - number_of_arguments;
+        let arguments = self.evaluation_stack
+            .split_off(first_argument)
+            .into_iter()
+            .map(|object| object.try_into().unwrap_or_else(|_| panic!("Invalid argument passed to EXTERNAL function {}", name)))
+            .collect::<Vec<Value>>();
+        let result = (external.handler)(&arguments);
+        self.evaluation_stack.push(Object::Value(result));
     }
 
     // This method calculates the next sequence shuffle index iteratively by calculating all the
@@ -792,21 +1752,31 @@ impl Story {
         let loop_index = (seq_count / num_elements) as u64;
         let iteration_index = seq_count % num_elements;
 
+        // the seed is based on a hash of the path, computed the same way as the official runtime
         let path_str = format!("{}", Object::Container(seq_container).path());
-        let hash = path_str.chars().fold(0, |acc, ch| acc + ch as u64);
-        let seed = hash + loop_index + self.story_seed;
-        let mut random = Pcg64::seed_from_u64(seed);
+        let hash = path_str.chars().fold(0, |acc: u64, ch| acc.wrapping_add(ch as u64));
+        let seed = hash.wrapping_add(loop_index).wrapping_add(self.story_seed);
+        let mut random = self.rng.borrow_mut();
+        random.reseed(seed as i64);
         let mut unpicked_indices = (0..num_elements).collect::<Vec<_>>();
-        (0..iteration_index).fold(0, move |_, _| {
-            let chosen: u64 = random.gen_range(0, unpicked_indices.len() as u64);
+        (0..=iteration_index).fold(0, move |_, _| {
+            let chosen = random.next() as u64 % unpicked_indices.len() as u64;
             unpicked_indices.remove(chosen as usize)
         })
     }
+
+    /// Reseeds the random number generator and returns the first number it generates.
+    fn next_random(&self, seed: u64) -> i64 {
+        let mut rng = self.rng.borrow_mut();
+        rng.reseed(seed as i64);
+        rng.next()
+    }
 }
 
 // Call stack
 impl Story {
-    fn current_thread(&self) -> &Thread {
+    /// The thread being executed.
+    pub fn current_thread(&self) -> &Thread {
         self.threads.last().unwrap()
     }
 
@@ -823,6 +1793,12 @@ impl Story {
         self.current_thread().elements.last().unwrap()
     }
 
+    /// The index of the current element within the current thread, which is how temporary variable
+    /// contexts are identified.
+    fn current_element_index(&self) -> usize {
+        self.current_thread().elements.len() - 1
+    }
+
     fn current_element_mut(&mut self) -> &mut Element {
         self.current_thread_mut().elements.last_mut().unwrap()
     }
@@ -835,6 +1811,18 @@ impl Story {
         self.current_element_mut().current_pointer = pointer;
     }
 
+    fn push_call_stack(&mut self, push_pop_type: PushPopType) {
+        let element = Element {
+            current_pointer: self.current_pointer(),
+            in_expression_evaluation: false,
+            temporary_variables: HashMap::default(),
+            push_pop_type,
+            evaluation_stack_size_when_called: self.evaluation_stack.len(),
+            function_start_in_output_stream: Some(self.output_stream.len()),
+        };
+        self.current_thread_mut().elements.push(element);
+    }
+
     fn pop_call_stack(&mut self, push_pop_type: Option<PushPopType>) {
         if self.current_element().push_pop_type == PushPopType::Function {
             self.trim_whitespace_from_function_end();
@@ -856,6 +1844,24 @@ impl Story {
 
 // Variables
 impl Story {
+    /// The value of the global variable with the given name, if there is one.
+    pub fn variable(&self, name: &str) -> Option<Value> {
+        self.get_variable_with_context(&name.to_owned(), VariableContext::Global)
+    }
+
+    /// Whether the story declares a global variable with the given name.
+    pub fn has_variable(&self, name: &str) -> bool {
+        self.global_variable_exists(&name.to_owned())
+    }
+
+    /// Sets the value of a global variable, which must already be declared by the story.
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        if !self.has_variable(name) {
+            panic!("Cannot assign to a variable ({}) that hasn't been declared in the story", name);
+        }
+        self.set_global_variable(name.to_owned(), Object::Value(value));
+    }
+
     fn get_variable_value(&self, variable: &String) -> Option<Value> {
         self.get_variable_with_context(variable, VariableContext::Unknown)
     }
@@ -908,9 +1914,14 @@ impl Story {
         let index = match context {
             VariableContext::Global => panic!("Cannot set temporary variable if it is a global variable"),
             VariableContext::Temporary(index) => index,
-            VariableContext::Unknown => self.current_thread().elements.len() - 1,
+            VariableContext::Unknown => self.current_element_index(),
         };
-        let old_value = self.current_thread().elements[index].temporary_variables.get(&name).cloned();
+        let old_value = self.current_thread().elements
+            .get(index)
+            .unwrap_or_else(|| panic!("Variable {} refers to a call stack frame ({}) which no longer exists", name, index))
+            .temporary_variables
+            .get(&name)
+            .cloned();
         if !is_new_declaration && old_value.is_none() {
             panic!("Variable {} is not defined in this context", name);
         }
@@ -923,7 +1934,9 @@ impl Story {
     }
 
     fn set_global_variable(&mut self, name: String, value: Object) {
-        let old_value = self.global_variables.get(&name).cloned();
+        let old_value = self.global_variables.get(&name)
+            .or_else(|| self.default_global_variables.get(&name))
+            .cloned();
         let new_value = match (old_value, value) {
             (Some(Object::Value(Value::List(List { origins, .. }))), Object::Value(Value::List(list))) => Object::Value(Value::List(list.with_empty_origins(&origins))),
             (_, value) => value,
@@ -974,7 +1987,7 @@ impl Story {
     // functions in the original implementation... questionable, right?
     fn context_for_variable_named(&self, name: &String) -> VariableContext {
         if self.current_element().temporary_variables.get(name).is_some() {
-            VariableContext::Temporary(self.current_thread().elements.len())
+            VariableContext::Temporary(self.current_element_index())
         } else {
             VariableContext::Global
         }
@@ -984,12 +1997,210 @@ impl Story {
         if self.global_variable_exists(name) {
             VariableContext::Global
         } else {
-            VariableContext::Temporary(self.current_thread().elements.len())
+            VariableContext::Temporary(self.current_element_index())
         }
     }
 
     fn global_variable_exists(&self, name: &String) -> bool {
         self.global_variables.get(name).is_some() || self.default_global_variables.get(name).is_some()
+}
+
+    /// Runs the story's declarations of global variables, and takes their values as the defaults.
+    fn reset_globals(&mut self) {
+        let mut declarations = Path::default();
+        declarations.join("global decl".to_owned());
+        if self.main_container.content_at_path(&declarations).is_some() {
+            let original_pointer = self.current_pointer();
+            self.choose_path(&declarations, false);
+            while self.can_continue() {
+                self.step().expect("limits can only be set once the story is created");
+            }
+            self.set_current_pointer(original_pointer);
+            self.did_safe_exit = false;
+        }
+        self.default_global_variables = self.global_variables.clone();
+    }
+}
+
+// Save state
+impl Story {
+    /// Saves the state of the story in progress (its position, call stack, variables, read counts,
+    /// choices and random seed) as JSON, which can be restored with `load_state`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a variable or the evaluation stack holds something other than a value, which
+    /// should not happen between calls to `continue_story`, or a non-finite float, which cannot
+    /// be represented in JSON.
+    pub fn save_state(&self) -> String {
+        let state = SaveState {
+            version: SaveState::VERSION,
+            threads: self.threads.iter().map(save_thread).collect(),
+            thread_counter: self.thread_counter,
+            global_variables: self.global_variables
+                .iter()
+                .map(|(name, object)| (name.clone(), save_value(object)))
+                .collect(),
+            evaluation_stack: self.evaluation_stack
+                .iter()
+                .map(|object| match object {
+                    Object::Void => None,
+                    object => Some(save_value(object)),
+                })
+                .collect(),
+            output_stream: self.output_stream
+                .iter()
+                .map(|object| match object {
+                    Object::Value(value) => OutputState::Value(value.clone()),
+                    Object::Glue(..) => OutputState::Glue,
+                    Object::Tag(tag) => OutputState::Tag(tag.text().to_owned()),
+                    Object::ControlCommand(ControlCommand::BeginString) => OutputState::BeginString,
+                    object => panic!("Cannot save {:?} in the output stream", object),
+                })
+                .collect(),
+            current_choices: self.current_choices
+                .iter()
+                .map(|choice| ChoiceState {
+                    text: choice.text.clone(),
+                    target_path: choice.target_path.clone(),
+                    is_invisible_default: choice.is_invisible_default,
+                    thread: save_thread(&choice.thread_at_generation),
+                })
+                .collect(),
+            current_turn_index: self.current_turn_index,
+            visit_counts: self.visit_counts.iter().map(|(path, &count)| (path.clone(), count)).collect(),
+            turn_indices: self.turn_indices.iter().map(|(path, &turn)| (path.clone(), turn)).collect(),
+            story_seed: self.story_seed,
+            previous_random: self.previous_random,
+        };
+        serde_json::to_string(&state).unwrap()
+    }
+
+    /// Restores a state saved by `save_state`. The state must have been saved from the same story,
+    /// as content is referred to by path.
+    ///
+    /// If the state cannot be loaded, the story is left unchanged.
+    pub fn load_state(&mut self, json: &str) -> Result<(), serde_json::Error> {
+        let state: SaveState = serde_json::from_str(json)?;
+        if state.version != SaveState::VERSION {
+            return Err(serde_json::Error::custom(format!("Save state version {} is not supported (expected {})", state.version, SaveState::VERSION)));
+        }
+        if state.threads.is_empty() {
+            return Err(serde_json::Error::custom("Save state has no threads"));
+        }
+
+        let threads = state.threads
+            .iter()
+            .map(|thread| self.load_thread(thread))
+            .collect::<Result<Vec<_>, _>>()?;
+        let current_choices = state.current_choices
+            .into_iter()
+            .map(|choice| Ok(Rc::new(Choice::new(
+                choice.text,
+                choice.target_path,
+                choice.is_invisible_default,
+                self.load_thread(&choice.thread)?,
+            ))))
+            .collect::<Result<Vec<_>, serde_json::Error>>()?;
+
+        self.threads = threads;
+        self.thread_counter = state.thread_counter;
+        self.global_variables = state.global_variables
+            .into_iter()
+            .map(|(name, value)| (name, Object::Value(value)))
+            .collect();
+        self.evaluation_stack = state.evaluation_stack
+            .into_iter()
+            .map(|value| value.map(Object::Value).unwrap_or(Object::Void))
+            .collect();
+        self.output_stream = state.output_stream
+            .into_iter()
+            .map(|object| match object {
+                OutputState::Value(value) => Object::Value(value),
+                OutputState::Glue => Object::Glue(Rc::new(Glue)),
+                OutputState::Tag(text) => Object::Tag(Rc::new(Tag::new(text))),
+                OutputState::BeginString => Object::ControlCommand(ControlCommand::BeginString),
+            })
+            .collect();
+        self.string_evaluation_depth = self.output_stream
+            .iter()
+            .filter(|object| **object == Object::ControlCommand(ControlCommand::BeginString))
+            .count();
+        self.output_sink_writer = OutputSinkWriter::default();
+        self.current_choices = current_choices;
+        self.current_turn_index = state.current_turn_index;
+        self.visit_counts = state.visit_counts.into_iter().collect();
+        self.turn_indices = state.turn_indices.into_iter().collect();
+        self.story_seed = state.story_seed;
+        self.previous_random = state.previous_random;
+
+        self.diverted_pointer = None;
+        self.did_safe_exit = false;
+        self.state_snapshot = None;
+        self.pending_traces.clear();
+        self.steps_this_continue = 0;
+        Ok(())
+    }
+
+    fn load_thread(&self, thread: &ThreadState) -> Result<Thread, serde_json::Error> {
+        let elements = thread.elements
+            .iter()
+            .map(|element| Ok(Element {
+                current_pointer: self.load_pointer(&element.path)?,
+                in_expression_evaluation: element.in_expression_evaluation,
+                temporary_variables: element.temporary_variables
+                    .iter()
+                    .map(|(name, value)| (name.clone(), Object::Value(value.clone())))
+                    .collect(),
+                push_pop_type: element.push_pop_type,
+                evaluation_stack_size_when_called: element.evaluation_stack_size_when_called,
+                function_start_in_output_stream: element.function_start_in_output_stream,
+            }))
+            .collect::<Result<Vec<_>, serde_json::Error>>()?;
+        if elements.is_empty() {
+            return Err(serde_json::Error::custom("Save state has a thread with no call stack"));
+        }
+        Ok(Thread {
+            elements,
+            index: thread.index,
+            previous_pointer: self.load_pointer(&thread.previous_path)?,
+        })
+    }
+
+    fn load_pointer(&self, path: &Option<Path>) -> Result<Pointer, serde_json::Error> {
+        match path {
+            None => Ok(Pointer::NULL),
+            Some(path) => self.pointer_at_path(path)
+                .ok_or_else(|| serde_json::Error::custom(format!("Content at path not found: {}", path))),
+        }
+    }
+}
+
+fn save_thread(thread: &Thread) -> ThreadState {
+    ThreadState {
+        index: thread.index,
+        elements: thread.elements
+            .iter()
+            .map(|element| ElementState {
+                path: element.current_pointer.path(),
+                in_expression_evaluation: element.in_expression_evaluation,
+                temporary_variables: element.temporary_variables
+                    .iter()
+                    .map(|(name, object)| (name.clone(), save_value(object)))
+                    .collect(),
+                push_pop_type: element.push_pop_type,
+                evaluation_stack_size_when_called: element.evaluation_stack_size_when_called,
+                function_start_in_output_stream: element.function_start_in_output_stream,
+            })
+            .collect(),
+        previous_path: thread.previous_pointer.path(),
+    }
+}
+
+fn save_value(object: &Object) -> Value {
+    match object {
+        Object::Value(value) => value.clone(),
+        object => panic!("Cannot save {:?}, as it is not a value", object),
     }
 }
 
@@ -999,3 +2210,493 @@ impl Story {
         // TODO: this is not yet needed, so it is not implemented
     }
 }
+
+fn is_newline(text: &str) -> bool {
+    text == "\n"
+}
+
+fn is_inline_whitespace(text: &str) -> bool {
+    text.chars().all(|ch| ch == ' ' || ch == '\t')
+}
+
+fn is_non_whitespace(text: &str) -> bool {
+    !is_newline(text) && !is_inline_whitespace(text)
+}
+
+/// Splits the newlines (and the inline whitespace around them) at the start and end of some text
+/// into separate pieces, so that they can be trimmed by glue and functions like any other newline.
+/// Returns `None` if there is nothing to split.
+fn split_head_tail_whitespace(text: &str) -> Option<Vec<String>> {
+    let bytes = text.as_bytes();
+    let mut head_first_newline = None;
+    let mut head_last_newline = None;
+    for (i, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'\n' => {
+                head_first_newline = head_first_newline.or(Some(i));
+                head_last_newline = Some(i);
+            }
+            b' ' | b'\t' => continue,
+            _ => break,
+        }
+    }
+    let mut tail_last_newline = None;
+    let mut tail_first_newline = None;
+    for (i, &byte) in bytes.iter().enumerate().rev() {
+        match byte {
+            b'\n' => {
+                tail_last_newline = tail_last_newline.or(Some(i));
+                tail_first_newline = Some(i);
+            }
+            b' ' | b'\t' => continue,
+            _ => break,
+        }
+    }
+    if head_first_newline.is_none() && tail_last_newline.is_none() {
+        return None;
+    }
+
+    let mut texts = vec![];
+    let mut inner_start = 0;
+    let mut inner_end = text.len();
+    if let (Some(first), Some(last)) = (head_first_newline, head_last_newline) {
+        if first > 0 {
+            texts.push(text[..first].to_owned());
+        }
+        texts.push("\n".to_owned());
+        inner_start = last + 1;
+    }
+    if let Some(first) = tail_first_newline {
+        inner_end = first;
+    }
+    if inner_end > inner_start {
+        texts.push(text[inner_start..inner_end].to_owned());
+    }
+    if let (Some(first), Some(last)) = (tail_first_newline, tail_last_newline) {
+        if head_last_newline.map(|head_last| first > head_last).unwrap_or(true) {
+            texts.push("\n".to_owned());
+            if last < text.len() - 1 {
+                texts.push(text[last + 1..].to_owned());
+            }
+        }
+    }
+    Some(texts)
+}
+
+/// Removes the inline whitespace from the start and end of each line, and collapses runs of inline
+/// whitespace into a single space.
+fn clean_output_whitespace(text: &str) -> String {
+    let mut cleaned = String::with_capacity(text.len());
+    let mut whitespace_start = None;
+    let mut start_of_line = 0;
+    for (i, ch) in text.char_indices() {
+        let is_inline_whitespace = ch == ' ' || ch == '\t';
+        if is_inline_whitespace && whitespace_start.is_none() {
+            whitespace_start = Some(i);
+        }
+        if !is_inline_whitespace {
+            if ch != '\n' && whitespace_start.is_some_and(|start| start > 0 && start != start_of_line) {
+                cleaned.push(' ');
+            }
+            whitespace_start = None;
+        }
+        if ch == '\n' {
+            start_of_line = i + 1;
+        }
+        if !is_inline_whitespace {
+            cleaned.push(ch);
+        }
+    }
+    cleaned
+}
+
+/// How the output changed since the last time it ended in a newline.
+enum NewlineStateChange {
+    NoChange,
+    ExtendedBeyondNewline,
+    NewlineRemoved,
+}
+
+impl NewlineStateChange {
+    fn between(previous_text: &str, current_text: &str, previous_tag_count: usize, current_tag_count: usize) -> Self {
+        let newline_still_exists = current_text.len() >= previous_text.len()
+            && !previous_text.is_empty()
+            && current_text.as_bytes()[previous_text.len() - 1] == b'\n';
+        if previous_tag_count == current_tag_count && previous_text.len() == current_text.len() && newline_still_exists {
+            return NewlineStateChange::NoChange;
+        }
+        if !newline_still_exists {
+            return NewlineStateChange::NewlineRemoved;
+        }
+        // a tag is always the start of a new line
+        if current_tag_count > previous_tag_count {
+            return NewlineStateChange::ExtendedBeyondNewline;
+        }
+        // new text which is only spaces and tabs may still be removed by glue
+        if current_text[previous_text.len()..].chars().any(|ch| ch != ' ' && ch != '\t') {
+            NewlineStateChange::ExtendedBeyondNewline
+        } else {
+            NewlineStateChange::NoChange
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_story() -> Story {
+        Story::new(Rc::new(Container::default()), ListDefinitions::new(vec![]))
+    }
+
+    #[test]
+    fn from_json_declares_globals() {
+        let story = Story::from_json(r##"{"inkVersion":19,"root":[["^Hello","\n","done",null],"done",{"global decl":["ev",5,{"VAR=":"x"},"/ev","end",null]}]}"##).unwrap();
+        assert_eq!(story.variable("x"), Some(Value::Int(5)));
+        assert!(story.can_continue());
+        assert_eq!(story.current_path().map(|path| path.to_string()), Some("0.0".to_owned()));
+        assert!(story.seed() < 100);
+    }
+
+    fn story(root: &str) -> Story {
+        Story::from_json(&format!(r#"{{"inkVersion":19,"root":{}}}"#, root)).unwrap()
+    }
+
+    #[test]
+    fn continue_story_line_by_line() {
+        let mut story = story(r#"[["^Hello","\n","^World","\n","end",null],"done",null]"#);
+        assert_eq!(story.continue_story(), "Hello\n");
+        assert!(story.can_continue());
+        assert_eq!(story.continue_story(), "World\n");
+        assert!(!story.can_continue());
+    }
+
+    #[test]
+    fn continue_story_follows_glue() {
+        let mut story = story(r#"[["^Hello ","\n","<>","^ world","\n","^Next","\n","end",null],"done",null]"#);
+        assert_eq!(story.continue_story(), "Hello world\n");
+        assert_eq!(story.continue_maximally(), "Next\n");
+    }
+
+    #[test]
+    fn continue_story_outputs_function_results() {
+        let mut story = story(r#"[["^Value: ","ev",{"f()":"f"},"out","/ev","\n","end",null],"done",{"f":["ev",5,"/ev","~ret",null]}]"#);
+        assert_eq!(story.continue_story(), "Value: 5\n");
+    }
+
+    #[test]
+    fn continue_story_trims_function_output() {
+        let mut story = story(r#"[["^A ",{"f()":"f"},"^ B","\n","end",null],"done",{"f":["\n","^text","\n","ev","void","/ev","~ret",null]}]"#);
+        assert_eq!(story.continue_story(), "A text B\n");
+    }
+
+    #[test]
+    fn continue_story_falls_back_to_ink_functions() {
+        let mut story = story(r#"[["ev",1,2,{"x()":"add","exArgs":2},"out","/ev","\n","end",null],"done",{"add":[{"temp=":"b"},{"temp=":"a"},"ev",{"VAR?":"a"},{"VAR?":"b"},"+","/ev","~ret",null]}]"#);
+        assert_eq!(story.continue_story(), "3\n");
+    }
+
+    #[test]
+    fn exceeding_a_limit_panics_with_the_error() {
+        let mut story = story(r#"[[{"->":"loop"},null],"done",{"loop":["^Loop",{"->":"loop"},null]}]"#);
+        story.set_limits(Limits { max_steps_per_continue: Some(10), ..Limits::default() });
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| story.continue_story())).unwrap_err();
+        match payload.downcast_ref::<LimitExceeded>() {
+            Some(LimitExceeded::Steps { limit: 10, .. }) => {}
+            other => panic!("expected a step limit error, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn try_continue_story_returns_exceeded_limits() {
+        let mut story = story(r#"[[{"->":"loop"},null],"done",{"loop":["^Loop",{"->":"loop"},null]}]"#);
+        story.set_limits(Limits { max_steps_per_continue: Some(10), ..Limits::default() });
+        match story.try_continue_story() {
+            Err(StoryError::LimitExceeded(LimitExceeded::Steps { limit: 10, .. })) => {}
+            other => panic!("expected a step limit error, found {:?}", other),
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSink(Vec<String>);
+
+    impl OutputSink for RecordingSink {
+        fn on_text(&mut self, text: &str) { self.0.push(format!("text {:?}", text)) }
+        fn on_tag(&mut self, tag: &str) { self.0.push(format!("tag {}", tag)) }
+        fn on_line_end(&mut self) { self.0.push("line end".to_owned()) }
+        fn on_choices(&mut self, choices: &[Rc<Choice>]) { self.0.push(format!("{} choices", choices.len())) }
+    }
+
+    #[test]
+    fn output_sink_receives_each_line_once() {
+        let mut story = story(r##"[["^Hello ","\n","<>","^world",{"#":"greeting"},"\n","^Next","\n","ev","str","^A","/str","/ev",{"*":".^.c-0","flg":20},"ev","str","^B","/str","/ev",{"*":".^.c-1","flg":20},{"c-0":["end",null],"c-1":["end",{"#f":1}]}],"done",null]"##);
+        let sink = Rc::new(RefCell::new(RecordingSink::default()));
+        story.set_output_sink(sink.clone());
+        assert_eq!(story.continue_story(), "Hello world\n");
+        assert_eq!(story.continue_story(), "Next\n");
+        assert_eq!(sink.borrow().0, vec![
+            r#"text "Hello""#,
+            r#"text " world""#,
+            "tag greeting",
+            "line end",
+            r#"text "Next""#,
+            "line end",
+            "2 choices",
+        ]);
+    }
+
+    #[test]
+    fn output_sink_text_matches_the_continued_text() {
+        let roots = [
+            r#"[["^Hello ","\n","<>","^ world","\n","^Next","\n","end",null],"done",null]"#,
+            r#"[["^A ",{"f()":"f"},"^ B","\n","end",null],"done",{"f":["\n","^text ","\n","ev","void","/ev","~ret",null]}]"#,
+            r#"[["^  Lots   of ","^ space ","\n","^ Here","\n","end",null],"done",null]"#,
+            r#"[["^One","\n","<>","\n","^Two","\n","end",null],"done",null]"#,
+        ];
+        for root in &roots {
+            let mut story = story(root);
+            let sink = Rc::new(RefCell::new(RecordingSink::default()));
+            story.set_output_sink(sink.clone());
+            let text = story.continue_maximally();
+
+            let mut sent = String::new();
+            for event in &sink.borrow().0 {
+                if event == "line end" {
+                    sent.push('\n');
+                } else if let Some(text) = event.strip_prefix("text ") {
+                    sent.push_str(&serde_json::from_str::<String>(text).unwrap());
+                }
+            }
+            assert_eq!(sent, text, "{}", root);
+        }
+    }
+
+    #[test]
+    fn continue_story_stops_at_choices() {
+        let mut story = story(r##"[["^Hello","\n","ev","str","^A","/str","/ev",{"*":".^.c-0","flg":20},"ev","str","^B","/str","/ev",{"*":".^.c-1","flg":20},{"c-0":["^Picked A","\n","end",null],"c-1":["^Picked B","\n","end",{"#f":1}]}],"done",null]"##);
+        assert!(story.current_choices().is_empty());
+        assert_eq!(story.continue_story(), "Hello\n");
+        assert!(!story.can_continue());
+        let choices = story.current_choices().iter().map(|choice| choice.text().to_owned()).collect::<Vec<_>>();
+        assert_eq!(choices, vec!["A", "B"]);
+        story.choose_choice_index(1);
+        assert_eq!(story.continue_story(), "Picked B\n");
+        assert_eq!(story.visit_count_at_path("0.c-1"), 1);
+    }
+
+    #[test]
+    fn try_continue_story_reports_running_out_of_content() {
+        let mut story = story(r#"[["^Hi","\n",null],null]"#);
+        assert_eq!(story.try_continue_story(), Err(StoryError::OutOfContent("ran out of content. Do you need a '-> DONE' or '-> END'?".to_owned())));
+    }
+
+    #[test]
+    fn random_numbers_continue_after_loading() {
+        let root = r#"[["ev",1,1000,"rnd","out","/ev","\n","ev",1,1000,"rnd","out","/ev","\n","end",null],"done",null]"#;
+        let mut story = story(root);
+        story.set_seed(3);
+        story.continue_story();
+        let saved = story.save_state();
+        let second = story.continue_story();
+
+        let mut loaded = self::story(root);
+        loaded.load_state(&saved).unwrap();
+        assert_eq!(loaded.seed(), 3);
+        assert_eq!(loaded.continue_story(), second);
+    }
+
+    #[test]
+    fn dot_net_random_matches_the_official_runtime() {
+        // `{~a|b|c} {RANDOM(1, 10)}` repeated, with the lines printed by the official runtime
+        let mut story = story(r##"[[{"->":"roll"},null],"done",{"roll":[["ev","visit",3,"seq","/ev","ev","du",0,"==","/ev",{"->":".^.s0","c":true},"ev","du",1,"==","/ev",{"->":".^.s1","c":true},"ev","du",2,"==","/ev",{"->":".^.s2","c":true},"nop",{"s0":["pop","^a",{"->":".^.^.23"},null],"s1":["pop","^b",{"->":".^.^.23"},null],"s2":["pop","^c",{"->":".^.^.23"},null],"#f":5}],"^ ","ev",1,10,"rnd","out","/ev","\n",{"->":"roll"},null]}]"##);
+        story.set_rng(DotNetRandom::default());
+        story.set_seed(7);
+        let lines = (0..6).map(|_| story.continue_story()).collect::<Vec<_>>();
+        assert_eq!(lines, vec!["b 2\n", "c 6\n", "a 2\n", "b 4\n", "c 2\n", "a 4\n"]);
+    }
+
+    #[test]
+    fn temporaries_are_passed_by_reference_through_nested_functions() {
+        // `~ temp t = 1`, then `~ add(t)`, where `add(ref x)` calls `inc(x)`, and `inc(ref y)` does `~ y = y + 1`
+        let mut story = story(r#"[["ev",1,"/ev",{"temp=":"t"},"ev",{"^var":"t","ci":-1},{"f()":"add"},"pop","/ev","ev",{"VAR?":"t"},"out","/ev","\n","end",null],"done",{
+            "add":[{"temp=":"x"},"ev",{"^var":"x","ci":-1},{"f()":"inc"},"pop","/ev","ev","void","/ev","~ret",null],
+            "inc":[{"temp=":"y"},"ev",{"VAR?":"y"},1,"+","/ev",{"temp=":"y","re":true},"ev","void","/ev","~ret",null]
+        }]"#);
+        assert_eq!(story.continue_story(), "2\n");
+    }
+
+    #[test]
+    fn globals_are_passed_by_reference_through_nested_functions() {
+        let mut story = story(r#"[["ev",{"^var":"g","ci":-1},{"f()":"add"},"pop","/ev","ev",{"VAR?":"g"},"out","/ev","\n","end",null],"done",{
+            "add":[{"temp=":"x"},"ev",{"^var":"x","ci":-1},{"f()":"inc"},"pop","/ev","ev","void","/ev","~ret",null],
+            "inc":[{"temp=":"y"},"ev",{"VAR?":"y"},1,"+","/ev",{"temp=":"y","re":true},"ev","void","/ev","~ret",null],
+            "global decl":["ev",1,{"VAR=":"g"},"/ev","end",null]
+        }]"#);
+        assert_eq!(story.continue_story(), "2\n");
+        assert_eq!(story.variable("g"), Some(Value::Int(2)));
+    }
+
+    #[test]
+    fn temporaries_are_passed_by_reference_into_tunnels() {
+        // `~ temp t = 1`, then `-> bump(t) ->`, where `bump(ref x)` does `~ x = x + 1`
+        let mut story = story(r#"[["ev",1,"/ev",{"temp=":"t"},"ev",{"^var":"t","ci":-1},"/ev",{"->t->":"bump"},"ev",{"VAR?":"t"},"out","/ev","\n","end",null],"done",{
+            "bump":[{"temp=":"x"},"ev",{"VAR?":"x"},1,"+","/ev",{"temp=":"x","re":true},"ev","void","/ev","->->",null]
+        }]"#);
+        assert_eq!(story.continue_story(), "2\n");
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let mut story = story(r##"[["^Hello","\n","ev","str","^A","/str","/ev",{"*":".^.c-0","flg":20},"ev","str","^B","/str","/ev",{"*":".^.c-1","flg":20},{"c-0":["ev",1,{"VAR=":"x","re":true},"/ev","^Picked A","\n","end",{"#f":1}],"c-1":["^Picked B","\n","end",{"#f":1}]}],"done",{"global decl":["ev",0,{"VAR=":"x"},"/ev","end",null]}]"##);
+        story.set_seed(42);
+        story.continue_story();
+        let saved = story.save_state();
+
+        story.choose_choice_index(0);
+        assert_eq!(story.continue_story(), "Picked A\n");
+        assert_eq!(story.variable("x"), Some(Value::Int(1)));
+        story.set_seed(7);
+
+        story.load_state(&saved).unwrap();
+        assert_eq!(story.variable("x"), Some(Value::Int(0)));
+        assert_eq!(story.seed(), 42);
+        assert_eq!(story.current_turn_index(), 0);
+        assert_eq!(story.current_text(), "Hello\n");
+        assert_eq!(story.current_choices().len(), 2);
+        story.choose_choice_index(1);
+        assert_eq!(story.continue_story(), "Picked B\n");
+        assert_eq!(story.visit_count_at_path("0.c-0"), 0);
+        assert_eq!(story.visit_count_at_path("0.c-1"), 1);
+
+        assert!(story.load_state("{}").is_err());
+        assert_eq!(story.visit_count_at_path("0.c-1"), 1);
+    }
+
+    /// A story with two knots, the second of which has a stitch that can be reached by name or by
+    /// index, and a function.
+    fn knots_story() -> Story {
+        story(r##"[[{"->":"start"},null],"done",{
+            "start":["ev",{"CNT?":".^"},"out","/ev","\n","ev",{"^->":"other.2"},"readc","out","/ev","\n","ev",3,{"f()":"double"},"out","/ev","\n","ev","str","^Go","/str","/ev",{"*":".^.c-0","flg":20},"done",{"c-0":[{"->":"other.part"},{"#f":1}],"#f":3}],
+            "other":["^Other","\n",["^Part","\n","ev",{"^->":"other.2"},"readc","out","/ev","\n","ev",{"^->":"start"},"turns","out","/ev","\n","end",{"#n":"part","#f":3}],null],
+            "double":[{"temp=":"x"},"ev",{"VAR?":"x"},2,"*","/ev","~ret",null]
+        }]"##)
+    }
+
+    #[test]
+    fn read_counts_are_shared_between_paths_to_a_container() {
+        let mut story = knots_story();
+        assert_eq!(story.continue_story(), "1\n");
+        assert_eq!(story.continue_story(), "0\n");
+        assert_eq!(story.continue_story(), "6\n");
+        assert_eq!(story.visit_count_at_path("start"), 1);
+        assert_eq!(story.turns_since("start"), 0);
+        assert_eq!(story.turns_since("other.part"), -1);
+
+        story.choose_choice_index(0);
+        assert_eq!(story.continue_story(), "Part\n");
+        // visited by name, read by index
+        assert_eq!(story.continue_story(), "1\n");
+        assert_eq!(story.continue_story(), "1\n");
+        assert_eq!(story.visit_count_at_path("other.part"), 1);
+        assert_eq!(story.visit_count_at_path("other.2"), 1);
+        assert_eq!(story.turns_since("other.2"), 0);
+        assert_eq!(story.turns_since("start"), 1);
+        assert_eq!(story.current_turn_index(), 1);
+        // containers which do not count visits are never counted
+        assert_eq!(story.visit_count_at_path("other"), 0);
+        assert_eq!(story.turns_since("other"), -1);
+
+        // the choice was once only
+        story.choose_path_string("start");
+        assert_eq!(story.continue_maximally(), "2\n1\n6\n");
+        assert!(story.current_choices().is_empty());
+    }
+
+    #[test]
+    fn story_structure_queries() {
+        let story = knots_story();
+        let paths = |paths: Vec<Path>| paths.iter().map(Path::to_string).collect::<Vec<_>>();
+        assert_eq!(paths(story.knots()), vec!["start", "other"]);
+        assert_eq!(paths(story.stitches()), vec!["other.part"]);
+        assert_eq!(paths(story.functions()), vec!["double"]);
+        assert!(story.has_path("other.part"));
+        assert!(story.has_path("other.2"));
+        assert!(story.has_path("start.c-0"));
+        assert!(!story.has_path("missing"));
+        assert!(!story.has_path("other.missing"));
+    }
+
+    #[test]
+    fn evaluate_function_while_waiting_for_a_choice() {
+        let mut story = knots_story();
+        story.continue_maximally();
+        let text = story.current_text();
+        assert_eq!(story.evaluate_function("double", &[Value::Int(4)]), (Some(Value::Int(8)), String::new()));
+        assert_eq!(story.current_text(), text);
+        assert_eq!(story.current_choices().len(), 1);
+        story.choose_choice_index(0);
+        assert_eq!(story.continue_story(), "Part\n");
+    }
+
+    #[test]
+    fn evaluate_function_returns_its_text() {
+        let mut story = story(r#"[["^Story","\n","end",null],"done",{"greet":[{"temp=":"name"},"^Hello, ","ev",{"VAR?":"name"},"out","/ev","\n","ev","void","/ev","~ret",null]}]"#);
+        let (result, text) = story.evaluate_function("greet", &[Value::String("Alice".to_owned())]);
+        assert_eq!(result, None);
+        assert_eq!(text, "Hello, Alice\n");
+        assert_eq!(story.evaluation_stack().len(), 0);
+        assert_eq!(story.continue_story(), "Story\n");
+    }
+
+    fn colours_story(content: &str) -> Story {
+        Story::from_json(&format!(r#"{{"inkVersion":19,"root":[[{},"\n","end",null],"done",null],"listDefs":{{"Colours":{{"red":1,"green":2,"blue":3}}}}}}"#, content)).unwrap()
+    }
+
+    #[test]
+    fn native_functions_are_called_with_arguments_in_order() {
+        let mut story = story(r#"[["ev",7,2,"-",10,"+",3,"/","out","/ev","\n","end",null],"done",null]"#);
+        assert_eq!(story.continue_story(), "5\n");
+    }
+
+    #[test]
+    fn list_from_int_without_an_item_keeps_its_origin() {
+        let mut story = colours_story(r#""ev","str","^Colours","/str",2,"listInt","out","str","^Colours","/str",5,"listInt","LIST_ALL","out","/ev""#);
+        assert_eq!(story.continue_story(), "greenred, green, blue\n");
+    }
+
+    #[test]
+    fn list_random_advances_the_previous_random_number() {
+        let mut story = colours_story(r#""ev",{"list":{"Colours.red":1,"Colours.green":2,"Colours.blue":3}},"lrnd","out","/ev""#);
+        story.set_seed(42);
+        let next_random = story.next_random(42);
+        let expected = ["red", "green", "blue"][next_random as usize % 3];
+        assert_eq!(story.continue_story(), format!("{}\n", expected));
+        assert_eq!(story.previous_random, next_random as u64);
+    }
+
+    #[test]
+    fn default_value_formatter() {
+        let story = empty_story();
+        assert_eq!(story.format_value(&Value::Float(1.5)), "1.5");
+        assert_eq!(story.format_value(&Value::Int(3)), "3");
+    }
+
+    #[test]
+    fn default_value_formatter_follows_numeric_mode() {
+        let mut story = empty_story();
+        assert_eq!(story.format_value(&Value::Float(0.1)), "0.1");
+        story.set_numeric_mode(NumericMode::CSharp);
+        assert_eq!(story.format_value(&Value::Float(0.1f32 as f64)), "0.1");
+        story.set_value_formatter(|value: &Value| format!("<{}>", value));
+        assert_eq!(story.format_value(&Value::Int(1)), "<1>");
+        story.clear_value_formatter();
+        assert_eq!(story.format_value(&Value::Int(1)), "1");
+    }
+
+    #[test]
+    fn strings_are_concatenated_with_floats_as_printed_in_csharp_mode() {
+        let mut story = story(r#"[["ev","str","^x","/str",0.1,"+","out",0.1,"out","/ev","\n","end",null],"done",null]"#);
+        story.set_numeric_mode(NumericMode::CSharp);
+        assert_eq!(story.continue_story(), "x0.10.1\n");
+    }
+}
@@ -0,0 +1,46 @@
+use std::any::Any;
+use std::fmt::{self, Display, Formatter};
+use super::LimitExceeded;
+
+/// The reason `Story::try_continue_story` failed to continue the story.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum StoryError {
+    /// The story exceeded one of its `Limits`.
+    LimitExceeded(LimitExceeded),
+    /// The story ran out of content without reaching choices, an `END` or a `DONE`, with a
+    /// description of what may be missing (such as a `~ return` at the end of a function).
+    OutOfContent(String),
+}
+
+impl Display for StoryError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            StoryError::LimitExceeded(error) => error.fmt(f),
+            StoryError::OutOfContent(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for StoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoryError::LimitExceeded(error) => Some(error),
+            StoryError::OutOfContent(..) => None,
+        }
+    }
+}
+
+impl From<LimitExceeded> for StoryError {
+    fn from(error: LimitExceeded) -> Self {
+        StoryError::LimitExceeded(error)
+    }
+}
+
+/// The message of a caught panic, whether it was raised by `panic!` or by `continue_story`
+/// exceeding a limit.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<String>().cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|message| message.to_string()))
+        .or_else(|| payload.downcast_ref::<LimitExceeded>().map(|error| error.to_string()))
+        .unwrap_or_default()
+}
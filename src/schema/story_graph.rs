@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use super::{Component, Container, ControlCommand, DivertTarget, NamedObject, Object, Path, PushPopType, Value, WalkItem};
+
+/// Whether a container name was generated by the compiler (for choices, gathers, etc.) rather than
+/// written by the author as the name of a knot or stitch.
+pub(crate) fn is_generated_name(name: &str) -> bool {
+    let numbered = |prefix: &str| name.starts_with(prefix) && name[prefix.len()..].chars().all(|ch| ch.is_ascii_digit());
+    name.is_empty()
+        || name.starts_with('$')
+        || name.contains(' ')
+        || name == "s"
+        || name == "b"
+        || numbered("c-")
+        || numbered("g-")
+}
+
+/// A knot of the story, along with the names of its stitches.
+#[derive(Clone, Debug)]
+pub struct Knot {
+    pub name: String,
+    pub stitches: Vec<String>,
+}
+
+/// The way in which one part of a story leads to another.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum EdgeKind {
+    Divert,
+    /// A choice, with the text of the choice (if it could be determined).
+    Choice(String),
+    Tunnel,
+    Function,
+}
+
+/// A connection from one knot or stitch to another.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub struct Edge {
+    pub from: Path,
+    pub to: Path,
+    pub kind: EdgeKind,
+}
+
+/// A map of the knots and stitches of a story, and the diverts, choices, tunnels and function calls
+/// between them.
+///
+/// Every edge is between nodes which are either a knot (`knot`), a stitch (`knot.stitch`), or the
+/// content at the root of the story before the first knot (the empty path). The graph implements
+/// `Display`, producing a Graphviz DOT document with each knot as a cluster.
+#[derive(Clone, Default, Debug)]
+pub struct StoryGraph {
+    pub knots: Vec<Knot>,
+    pub edges: Vec<Edge>,
+}
+
+impl StoryGraph {
+    pub fn build(root: &Container) -> Self {
+        let mut graph = StoryGraph::default();
+        for knot in authored_children(root) {
+            let stitches = authored_children(knot)
+                .map(|stitch| stitch.name().to_owned())
+                .collect();
+            graph.knots.push(Knot { name: knot.name().to_owned(), stitches });
+        }
+
+        let mut edges = BTreeSet::new();
+        graph.collect_edges(root, &mut edges);
+        graph.edges = edges.into_iter().collect();
+        graph
+    }
+
+    fn collect_edges(&self, root: &Container, edges: &mut BTreeSet<Edge>) {
+        // whether each of the containers being walked is building a string, and the text of the
+        // choice being built
+        let mut strings: Vec<(bool, String)> = vec![];
+        // the text of each choice, by the path of the container its content is in, and the last
+        // divert in each of those containers, which is where the choice leads. The content of a
+        // choice is always named-only content, so it's walked after the choice point itself.
+        let mut choices: BTreeMap<Path, String> = BTreeMap::new();
+        let mut choice_targets: BTreeMap<Path, Path> = BTreeMap::new();
+        root.walk(&mut |path, item| {
+            let object = match item {
+                WalkItem::Container(..) => {
+                    strings.truncate(path.parts.len());
+                    strings.push((false, String::new()));
+                    return;
+                }
+                WalkItem::Object(object, _) => object,
+            };
+            let (in_string, choice_text) = &mut strings[path.parts.len() - 1];
+            match object {
+                Object::ControlCommand(ControlCommand::BeginString) => *in_string = true,
+                Object::ControlCommand(ControlCommand::EndString) => *in_string = false,
+                Object::Value(Value::String(text)) if *in_string => choice_text.push_str(text),
+                _ => {}
+            }
+
+            let edge = match object {
+                Object::Divert(divert) => match &divert.target {
+                    DivertTarget::Path(target) => {
+                        let container = path.without_last_component();
+                        let kind = match (divert.pushes_to_stack, divert.stack_push_type) {
+                            (false, _) if choices.contains_key(&container) => {
+                                choice_targets.insert(container, path.appending(target));
+                                return;
+                            }
+                            (false, _) => EdgeKind::Divert,
+                            (true, PushPopType::Tunnel) => EdgeKind::Tunnel,
+                            (true, _) => EdgeKind::Function,
+                        };
+                        Some((path.appending(target), kind))
+                    }
+                    _ => None,
+                },
+                Object::ChoicePoint(choice_point) => {
+                    let text = std::mem::take(choice_text);
+                    choices.insert(path.appending(&choice_point.path_on_choice), text.trim().to_owned());
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some((target, kind)) = edge {
+                let from = self.node_containing(path);
+                let to = self.node_containing(&target);
+                // diverts within the same node, such as to a gather, are not interesting
+                if from != to || kind != EdgeKind::Divert {
+                    edges.insert(Edge { from, to, kind });
+                }
+            }
+        });
+
+        for (container, target) in choice_targets {
+            let from = self.node_containing(&container);
+            let to = self.node_containing(&target);
+            edges.insert(Edge { from, to, kind: EdgeKind::Choice(choices.remove(&container).unwrap_or_default()) });
+        }
+    }
+
+    /// The path of the knot or stitch that contains the content at this path.
+    pub fn node_containing(&self, path: &Path) -> Path {
+        let name_at = |index: usize| match path.parts.get(index) {
+            Some(Component::Name(name)) => Some(name.as_str()),
+            _ => None,
+        };
+        let knot = match name_at(0).and_then(|name| self.knots.iter().find(|knot| knot.name == name)) {
+            Some(knot) => knot,
+            None => return Path::default(),
+        };
+        let mut node = Path::default();
+        node.join(knot.name.clone());
+        if let Some(stitch) = name_at(1).filter(|name| knot.stitches.iter().any(|stitch| stitch == name)) {
+            node.join(stitch.to_owned());
+        }
+        node
+    }
+}
+
+/// The knots of a root container, or the stitches of a knot: the containers directly within it
+/// which were named by the author.
+fn authored_children(container: &Container) -> impl Iterator<Item = &Container> {
+    container.content
+        .iter()
+        .filter_map(|object| match object {
+            Object::Container(child) => Some(&**child),
+            _ => None,
+        })
+        .chain(container.named_only_content.iter().map(|child| &**child))
+        .filter(|child| child.has_valid_name() && !is_generated_name(child.name()))
+}
+
+fn dot_id(path: &Path) -> String {
+    if path.is_empty() {
+        "\"(root)\"".to_owned()
+    } else {
+        dot_string(&path.to_string())
+    }
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Display for StoryGraph {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "digraph story {{")?;
+        writeln!(f, "  {} [shape=doublecircle, label=\"start\"];", dot_id(&Path::default()))?;
+        for knot in &self.knots {
+            let mut knot_path = Path::default();
+            knot_path.join(knot.name.clone());
+            writeln!(f, "  subgraph {} {{", dot_string(&format!("cluster_{}", knot.name)))?;
+            writeln!(f, "    label={};", dot_string(&knot.name))?;
+            writeln!(f, "    {} [label={}];", dot_id(&knot_path), dot_string(&knot.name))?;
+            for stitch in &knot.stitches {
+                let mut stitch_path = knot_path.clone();
+                stitch_path.join(stitch.clone());
+                writeln!(f, "    {} [label={}];", dot_id(&stitch_path), dot_string(stitch))?;
+            }
+            writeln!(f, "  }}")?;
+        }
+        for edge in &self.edges {
+            let attributes = match &edge.kind {
+                EdgeKind::Divert => String::new(),
+                EdgeKind::Choice(text) => format!(" [label={}]", dot_string(text)),
+                EdgeKind::Tunnel => " [style=dashed, label=\"tunnel\"]".to_owned(),
+                EdgeKind::Function => " [style=dotted, label=\"function\"]".to_owned(),
+            };
+            writeln!(f, "  {} -> {}{};", dot_id(&edge.from), dot_id(&edge.to), attributes)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Story;
+
+    #[test]
+    fn choices_lead_to_where_their_content_diverts() {
+        let json = r##"{"inkVersion":19,"root":[[{"->":"start"},null],"done",{"start":[["ev","str","^Go left","/str","/ev",{"*":".^.c-0","flg":20},"ev","str","^Stay","/str","/ev",{"*":".^.c-1","flg":20},{"c-0":["\n",{"->":"left"},{"#f":5}],"c-1":["\n",{"->":"start"},{"#f":5}]}],{"#f":1}],"left":["^Gone","\n","end",{"#f":1}]}]}"##;
+        let story = Story::from_json(json).unwrap();
+
+        let edges = story.graph().edges
+            .into_iter()
+            .map(|edge| (edge.from.to_string(), edge.to.to_string(), edge.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(edges, vec![
+            ("".to_owned(), "start".to_owned(), EdgeKind::Divert),
+            ("start".to_owned(), "left".to_owned(), EdgeKind::Choice("Go left".to_owned())),
+            ("start".to_owned(), "start".to_owned(), EdgeKind::Choice("Stay".to_owned())),
+        ]);
+    }
+}
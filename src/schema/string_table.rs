@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use super::{Container, Object, Pointer, Value, WalkItem};
+
+/// A table of the literal text in a story, keyed by the path to each piece of text.
+///
+/// A `StringTable` can be extracted from a story (`Story::extract_string_table`), exported for
+/// translation, and then loaded back into a `Story` (`Story::set_string_table`), after which any
+/// text found in the table will be substituted for the original text as it is output.
+///
+/// Text is extracted in the same fragments the compiler emitted it, so interpolated values (e.g.
+/// `Hello {name}!`) are left out of the table and continue to be printed as usual.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StringTable {
+    entries: BTreeMap<String, String>,
+}
+
+impl StringTable {
+    /// Extracts every piece of literal text, including choice text, from the content of a container
+    /// and all of its descendants. Text which is only whitespace (such as line breaks) is skipped.
+    pub fn extract(root: &Container) -> Self {
+        let mut entries = BTreeMap::new();
+        root.walk(&mut |path, item| {
+            if let WalkItem::Object(Object::Value(Value::String(text)), _) = item {
+                if !text.trim().is_empty() {
+                    entries.insert(path.to_string(), text.clone());
+                }
+            }
+        });
+        Self { entries }
+    }
+
+    /// The key of the text that the pointer points to, or `None` if the pointer is null.
+    pub(crate) fn key_at(pointer: &Pointer) -> Option<String> {
+        pointer.index?;
+        pointer.path().map(|path| path.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, key: String, text: String) {
+        self.entries.insert(key, text);
+    }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    /// Iterates over all the entries in the table, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, text)| (key.as_str(), text.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Story;
+
+    #[test]
+    fn translated_text_is_output() {
+        let json = r#"{"inkVersion":19,"root":[["^Hello","\n","^World","\n","end",null],"done",{"knot":["^In a knot","\n","end",null]}]}"#;
+        let mut story = Story::from_json(json).unwrap();
+        let table = story.extract_string_table();
+        let entries = table.iter().collect::<Vec<_>>();
+        assert_eq!(entries, vec![("0.0", "Hello"), ("0.2", "World"), ("knot.0", "In a knot")]);
+
+        // translated by exporting and importing the table as JSON
+        let mut exported: serde_json::Value = serde_json::to_value(&table).unwrap();
+        exported["0.0"] = "Bonjour".into();
+        exported["knot.0"] = "Dans un nœud".into();
+        story.set_string_table(serde_json::from_value(exported).unwrap());
+
+        assert_eq!(story.continue_story(), "Bonjour\n");
+        assert_eq!(story.continue_story(), "World\n");
+        story.choose_path_string("knot");
+        assert_eq!(story.continue_story(), "Dans un nœud\n");
+
+        story.clear_string_table();
+        story.choose_path_string("knot");
+        assert_eq!(story.continue_story(), "In a knot\n");
+    }
+}
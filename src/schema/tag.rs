@@ -1,10 +1,20 @@
+use super::{TagParser, TagValue};
+
 #[derive(Clone, Debug)]
 pub struct Tag {
     text: String,
 }
 
 impl Tag {
+    pub(crate) fn new(text: String) -> Self {
+        Tag { text }
+    }
+
     pub fn text(&self) -> &str {
         self.text.as_str()
     }
+
+    pub fn parse(&self, parser: &TagParser) -> TagValue {
+        parser.parse(&self.text)
+    }
 }
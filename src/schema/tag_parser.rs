@@ -0,0 +1,113 @@
+/// A tag which has been parsed into structured metadata by a `TagParser`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum TagValue {
+    /// A tag with no value, such as `#important`.
+    Flag(String),
+    /// A tag with a key and one or more values, such as `#speaker: Alice` or `#sfx door, creak`.
+    KeyValue { key: String, values: Vec<String> },
+}
+
+impl TagValue {
+    /// The key of the tag. For flags, this is the whole flag.
+    pub fn key(&self) -> &str {
+        match self {
+            TagValue::Flag(key) => key,
+            TagValue::KeyValue { key, .. } => key,
+        }
+    }
+
+    /// The values of the tag. Flags have no values.
+    pub fn values(&self) -> &[String] {
+        match self {
+            TagValue::Flag(..) => &[],
+            TagValue::KeyValue { values, .. } => values,
+        }
+    }
+
+    /// The first value of the tag, if any.
+    pub fn value(&self) -> Option<&str> {
+        self.values().first().map(String::as_str)
+    }
+
+    pub fn is_flag(&self) -> bool {
+        matches!(self, TagValue::Flag(..))
+    }
+}
+
+/// Parses the text of tags (`Tag::text`, `Story::current_tags`) into `TagValue`s, following the
+/// common `#key: value` conventions.
+///
+/// The key ends at the first of the `key_separators` found in the tag, and everything after is
+/// split into values by the `value_separator`. With the default separators, all of `#speaker: Alice`,
+/// `#mood:angry` and `#sfx door_open` are parsed as a key and a single value, while `#important` is
+/// parsed as a flag.
+#[derive(Clone, Debug)]
+pub struct TagParser {
+    pub key_separators: Vec<char>,
+    pub value_separator: Option<char>,
+}
+
+impl Default for TagParser {
+    fn default() -> Self {
+        TagParser {
+            key_separators: vec![':', ' '],
+            value_separator: Some(','),
+        }
+    }
+}
+
+impl TagParser {
+    pub fn parse(&self, tag: &str) -> TagValue {
+        let tag = tag.trim();
+        let (key, rest) = match tag.find(|ch| self.key_separators.contains(&ch)) {
+            Some(index) => {
+                // a key may be separated by several separators, as in `speaker : Alice`
+                let rest = tag[index..].trim_start_matches(|ch: char| self.key_separators.contains(&ch) || ch.is_whitespace());
+                (tag[..index].trim(), rest.trim())
+            }
+            None => (tag, ""),
+        };
+
+        if rest.is_empty() {
+            return TagValue::Flag(key.to_owned());
+        }
+
+        let values = match self.value_separator {
+            Some(separator) => rest
+                .split(separator)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            None => vec![rest.to_owned()],
+        };
+
+        TagValue::KeyValue { key: key.to_owned(), values }
+    }
+
+    pub fn parse_all<'a, I: IntoIterator<Item = &'a String>>(&self, tags: I) -> Vec<TagValue> {
+        tags.into_iter()
+            .map(|tag| self.parse(tag))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_value(key: &str, values: &[&str]) -> TagValue {
+        TagValue::KeyValue { key: key.to_owned(), values: values.iter().map(|value| value.to_string()).collect() }
+    }
+
+    #[test]
+    fn parse_with_default_separators() {
+        let parser = TagParser::default();
+        assert_eq!(parser.parse("speaker: Alice"), key_value("speaker", &["Alice"]));
+        assert_eq!(parser.parse("speaker : Alice"), key_value("speaker", &["Alice"]));
+        assert_eq!(parser.parse("mood:angry"), key_value("mood", &["angry"]));
+        assert_eq!(parser.parse("sfx door, creak"), key_value("sfx", &["door", "creak"]));
+        assert_eq!(parser.parse(" important "), TagValue::Flag("important".to_owned()));
+        assert_eq!(parser.parse("empty: "), TagValue::Flag("empty".to_owned()));
+    }
+}
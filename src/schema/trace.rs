@@ -0,0 +1,127 @@
+use std::io::Write;
+use super::{Object, Path};
+
+/// Describes an object that is about to be executed by a `Story`.
+#[derive(Debug)]
+pub struct TraceEvent<'a> {
+    /// The path to the object.
+    pub path: Path,
+    pub object: &'a Object,
+    /// The number of values on the evaluation stack before the object is executed.
+    pub evaluation_stack_depth: usize,
+    /// The number of frames in the call stack of the current thread.
+    pub call_stack_depth: usize,
+}
+
+/// A `TraceEvent` which is held back while the story is looking ahead past the end of a line, as
+/// the objects executed then may be rewound and executed again later.
+#[derive(Clone, Debug)]
+pub(crate) struct PendingTrace {
+    pub(crate) path: Path,
+    pub(crate) object: Object,
+    pub(crate) evaluation_stack_depth: usize,
+    pub(crate) call_stack_depth: usize,
+}
+
+impl PendingTrace {
+    pub(crate) fn event(&self) -> TraceEvent<'_> {
+        TraceEvent {
+            path: self.path.clone(),
+            object: &self.object,
+            evaluation_stack_depth: self.evaluation_stack_depth,
+            call_stack_depth: self.call_stack_depth,
+        }
+    }
+}
+
+/// Receives a `TraceEvent` for every object executed by a `Story`, once installed using
+/// `Story::set_tracer`.
+pub trait Tracer {
+    fn trace(&mut self, event: &TraceEvent);
+}
+
+impl<F> Tracer for F where F: FnMut(&TraceEvent) {
+    fn trace(&mut self, event: &TraceEvent) {
+        self(event)
+    }
+}
+
+/// A `Tracer` which writes a human readable log of execution, one object per line, indented by
+/// the depth of the call stack.
+///
+/// Errors while writing are ignored, as tracing should never interrupt the story.
+#[derive(Debug)]
+pub struct TraceWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Tracer for TraceWriter<W> {
+    fn trace(&mut self, event: &TraceEvent) {
+        let indent = "  ".repeat(event.call_stack_depth.saturating_sub(1));
+        let _ = writeln!(
+            self.writer,
+            "{}{}: {} [eval stack: {}]",
+            indent,
+            event.path,
+            event.object,
+            event.evaluation_stack_depth,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use super::super::Story;
+
+    #[test]
+    fn trace_writer_logs_each_object_by_path() {
+        let json = r#"{"inkVersion":19,"root":[[{"->":"start"},null],"done",{"start":["ev",{"f()":"one"},"out","/ev","\n","end",null],"one":["ev",1,"/ev","~ret",null]}]}"#;
+        let mut story = Story::from_json(json).unwrap();
+        let tracer = Rc::new(RefCell::new(TraceWriter::new(vec![])));
+        story.set_tracer(tracer.clone());
+        story.continue_story();
+        story.clear_tracer();
+
+        let log = Rc::try_unwrap(tracer).unwrap().into_inner().into_inner();
+        let log = String::from_utf8(log).unwrap();
+        assert_eq!(log.lines().collect::<Vec<_>>(), vec![
+            "0.0: divert -> start [eval stack: 0]",
+            "start.0: EvalStart [eval stack: 0]",
+            "start.1: call -> one [eval stack: 0]",
+            "  one.0: EvalStart [eval stack: 0]",
+            "  one.1: 1 [eval stack: 0]",
+            "  one.2: EvalEnd [eval stack: 1]",
+            "  one.3: PopFunction [eval stack: 1]",
+            "start.2: EvalOutput [eval stack: 1]",
+            "start.3: EvalEnd [eval stack: 0]",
+            r#"start.4: "\n" [eval stack: 0]"#,
+            "start.5: End [eval stack: 0]",
+        ]);
+    }
+
+    #[test]
+    fn lines_are_traced_once_despite_looking_ahead() {
+        let json = r#"{"inkVersion":19,"root":[["^One","\n","^Two","\n","<>","^ too","\n","^Three","\n","end",null],"done",null]}"#;
+        let mut story = Story::from_json(json).unwrap();
+        let paths = Rc::new(RefCell::new(vec![]));
+        let traced = paths.clone();
+        story.set_tracer(Rc::new(RefCell::new(move |event: &TraceEvent| traced.borrow_mut().push(event.path.to_string()))));
+        assert_eq!(story.continue_maximally(), "One\nTwo too\nThree\n");
+
+        let expected = (0..10).map(|index| format!("0.{}", index)).collect::<Vec<_>>();
+        assert_eq!(*paths.borrow(), expected);
+    }
+}
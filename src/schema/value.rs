@@ -1,15 +1,34 @@
 use std::convert::TryInto;
+use std::fmt::{self, Display, Formatter};
+use serde::{Serialize, Serializer, Deserialize};
+use serde::ser::Error as _;
 use super::{TryAsRef, VariableContext, List, Path};
 
-#[derive(Clone, PartialEq, Debug)]
-pub struct VariablePointer { 
+/// A reference to a variable, as used by ink's `ref` parameters.
+///
+/// Serializes as `{"name": "gold", "context": "Global"}`, where the context is one of `"Unknown"`,
+/// `"Global"` or `{"Temporary": index}`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct VariablePointer {
     pub(crate) name: String,
     pub(crate) context: VariableContext,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+/// A value in an ink story.
+///
+/// # Serialization
+///
+/// Values serialize as an object with a single key naming the type of value, such as `{"Int": 3}`,
+/// `{"Float": 1.5}`, `{"String": "hello"}` or `{"DivertTarget": "knot.stitch"}`. Lists serialize as
+/// `{"List": {"origins": ["Inventory"], "items": [{"origin": "Inventory", "name": "sword", "value": 1}]}}`
+/// and variable pointers as `{"VariablePointer": {"name": "gold", "context": "Global"}}`. Paths are
+/// serialized as their string representation (see `Path`).
+///
+/// Infinite and NaN floats fail to serialize, as they cannot be represented in JSON.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Value {
     Int(i64),
+    #[serde(serialize_with = "serialize_finite")]
     Float(f64),
     List(List),
     String(String),
@@ -18,6 +37,13 @@ pub enum Value {
     VariablePointer(VariablePointer),
 }
 
+fn serialize_finite<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if !value.is_finite() {
+        return Err(S::Error::custom(format!("Cannot serialize the non-finite float {}", value)));
+    }
+    serializer.serialize_f64(*value)
+}
+
 impl Value {
     /// Checks the truthiness of the Value:
     /// *   Int: value is not 0
@@ -40,6 +66,29 @@ impl Value {
     }
 }
 
+/// The text of a value, as printed by a story with the default `ValueFormatter`. Floats use Rust's
+/// formatting, so unlike the official runtime, large and small floats are printed in full rather
+/// than with an exponent (`10000000000` rather than `1E+10`), and non-finite floats are printed the
+/// way Rust prints them (`inf`, `-inf` and `NaN`).
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::List(list) => {
+                let names: Vec<&str> = list.items
+                    .iter()
+                    .map(|entry| entry.name.as_str())
+                    .collect();
+                write!(f, "{}", names.join(", "))
+            }
+            Value::DivertTarget(path) => write!(f, "DivertTargetValue({})", path),
+            Value::VariablePointer(pointer) => write!(f, "VariablePointerValue({})", pointer.name),
+        }
+    }
+}
+
 impl From<bool> for Value {
     fn from(value: bool) -> Self {
         Self::Int(if value { 1 } else { 0 })
@@ -196,3 +245,38 @@ impl TryInto<VariablePointer> for Value {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ListEntry;
+
+    fn round_trip(value: &Value) -> Value {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let list = vec![ListEntry::new("Colours".to_owned(), "red".to_owned(), 1)].into_iter().collect::<List>();
+        let values = vec![
+            Value::Int(3),
+            Value::Float(1.5),
+            Value::List(list),
+            Value::String("hello".to_owned()),
+            Value::DivertTarget("knot.stitch".parse().unwrap()),
+            Value::VariablePointer(VariablePointer { name: "gold".to_owned(), context: VariableContext::Global }),
+            Value::VariablePointer(VariablePointer { name: "x".to_owned(), context: VariableContext::Temporary(2) }),
+        ];
+        for value in &values {
+            assert_eq!(&round_trip(value), value);
+        }
+        assert_eq!(serde_json::to_string(&values[5]).unwrap(), r#"{"VariablePointer":{"name":"gold","context":"Global"}}"#);
+    }
+
+    #[test]
+    fn non_finite_floats_fail_to_serialize() {
+        assert!(serde_json::to_string(&Value::Float(f64::INFINITY)).is_err());
+        assert!(serde_json::to_string(&Value::Float(f64::NAN)).is_err());
+        assert_eq!(serde_json::to_string(&Value::Float(-2.5)).unwrap(), r#"{"Float":-2.5}"#);
+    }
+}
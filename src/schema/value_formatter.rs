@@ -0,0 +1,36 @@
+use super::{NumericMode, Value};
+
+/// Converts a `Value` into the text that is written to the output stream when the value is printed
+/// by the story (e.g. `{gold}` in ink).
+///
+/// A `ValueFormatter` can be installed on a `Story` using `Story::set_value_formatter`, allowing the
+/// host to localize numbers, join lists in a friendlier way, and so on.
+pub trait ValueFormatter {
+    fn format(&self, value: &Value) -> String;
+}
+
+/// The default `ValueFormatter`, which formats values the same way as the official runtime does,
+/// except for floats that the official runtime would print with an exponent or which aren't finite
+/// (see the `Display` implementation of `Value`).
+///
+/// When the `numeric_mode` is `NumericMode::CSharp`, floats are printed with the precision of a
+/// 32-bit float, matching the official runtime.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CanonicalValueFormatter {
+    pub numeric_mode: NumericMode,
+}
+
+impl ValueFormatter for CanonicalValueFormatter {
+    fn format(&self, value: &Value) -> String {
+        match (self.numeric_mode, value) {
+            (NumericMode::CSharp, Value::Float(value)) => format!("{}", *value as f32),
+            _ => value.to_string(),
+        }
+    }
+}
+
+impl<F> ValueFormatter for F where F: Fn(&Value) -> String {
+    fn format(&self, value: &Value) -> String {
+        self(value)
+    }
+}
@@ -1,7 +1,14 @@
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+use serde::{Serialize, Deserialize};
+
+/// Where a variable lives, which is needed to follow references (`ref` parameters) to variables
+/// in other call stack frames.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum VariableContext {
+    /// Not yet resolved: the variable is looked up in the globals, then the current frame.
     Unknown,
     Global,
+    /// A temporary variable of the call stack frame at this index in the current thread, where
+    /// 0 is the outermost frame.
     Temporary(usize),
 }
 